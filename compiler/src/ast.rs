@@ -0,0 +1,362 @@
+use crate::types::Span;
+
+pub struct Module {
+    pub name: String,
+    pub imports: Vec<Import>,
+    pub statements: Vec<Statement>,
+}
+
+pub struct Import {
+    pub module: String,
+    pub span: Span,
+}
+
+pub type Block = Vec<Statement>;
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    Let {
+        name: String,
+        ty: Option<Type>,
+        value: Option<Expression>,
+    },
+    Const {
+        name: String,
+        ty: Option<Type>,
+        value: Expression,
+    },
+    Assign {
+        target: Expression,
+        operator: Option<BinaryOperator>,
+        value: Expression,
+    },
+    Update {
+        target: Expression,
+        operator: UpdateOperator,
+        prefix: bool,
+    },
+    Expression(Expression),
+    If {
+        branches: Vec<(Expression, Block)>,
+        else_body: Option<Block>,
+    },
+    Loop {
+        body: Block,
+    },
+    While {
+        condition: Expression,
+        body: Block,
+    },
+    For {
+        variable: String,
+        /// The second variable of `loop key, value in map`, which also
+        /// walks the indices and elements of a list or string.
+        value_variable: Option<String>,
+        iterable: Expression,
+        body: Block,
+    },
+    /// The first arm whose pattern matches the value, and whose guard
+    /// holds, runs.
+    Match {
+        value: Expression,
+        arms: Vec<Arm>,
+    },
+    /// `catch` runs if the body throws, with what was thrown bound to
+    /// `variable`, and `finally` runs however the `try` is left.
+    Try {
+        body: Block,
+        variable: Option<String>,
+        catch_body: Option<Block>,
+        finally_body: Option<Block>,
+    },
+    Throw(Expression),
+    /// Hands a value to whatever is walking the generator the statement is
+    /// in, and waits there until the next value is asked for.
+    Yield(Expression),
+    Break,
+    Continue,
+    Return(Option<Expression>),
+    Function {
+        name: String,
+        params: Vec<Parameter>,
+        return_type: Option<Type>,
+        body: Block,
+    },
+    /// A method can be called on any value, unless it names a struct as its
+    /// `receiver`, as in `method Bag.iter(self)`. It is then only called on
+    /// objects of that struct, and takes their calls before any method of
+    /// the same name that doesn't name one.
+    Method {
+        name: String,
+        receiver: Option<String>,
+        params: Vec<Parameter>,
+        return_type: Option<Type>,
+        body: Block,
+    },
+    /// A struct's fields are the parameters of its constructor, in order.
+    Struct {
+        name: String,
+        fields: Vec<Parameter>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Block,
+    /// The pattern and guard, which unreachable arms are reported at.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A literal, matched with `==`.
+    Literal(Expression),
+    /// Numbers and characters from `start` up to but not including `end`.
+    /// Either bound can be left out.
+    Range {
+        start: Option<Expression>,
+        end: Option<Expression>,
+    },
+    /// A type name, matching every value of that type.
+    Type(Type),
+    /// `[first, second, rest...]`, matching lists of exactly as many items,
+    /// or at least as many with a rest. Each item is bound to its name, and
+    /// the rest to a list of the others. Items named `_` aren't bound.
+    List {
+        items: Vec<String>,
+        rest: Option<String>,
+    },
+}
+
+impl Pattern {
+    /// The names the pattern binds, in order.
+    pub fn bindings(&self) -> Vec<&str> {
+        match self {
+            Pattern::List { items, rest } => items
+                .iter()
+                .chain(rest)
+                .map(String::as_str)
+                .filter(|&name| name != "_")
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Whether a body makes its function, method or lambda a generator, by
+/// holding a `yield` outside of the lambdas in it.
+pub fn yields(block: &Block) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        StatementKind::Yield(_) => true,
+        StatementKind::If {
+            branches,
+            else_body,
+        } => {
+            branches.iter().any(|(_, body)| yields(body)) || else_body.as_ref().is_some_and(yields)
+        }
+        StatementKind::Loop { body }
+        | StatementKind::While { body, .. }
+        | StatementKind::For { body, .. } => yields(body),
+        StatementKind::Match { arms, .. } => arms.iter().any(|arm| yields(&arm.body)),
+        StatementKind::Try {
+            body,
+            catch_body,
+            finally_body,
+            ..
+        } => {
+            yields(body)
+                || catch_body.as_ref().is_some_and(yields)
+                || finally_body.as_ref().is_some_and(yields)
+        }
+        _ => false,
+    })
+}
+
+/// A parameter of a function, method or lambda, or a field of a struct.
+/// A parameter's default is used when its argument is missing or `null`,
+/// and a rest parameter collects any extra arguments into a list.
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub ty: Option<Type>,
+    pub default: Option<Expression>,
+    pub rest: bool,
+    pub span: Span,
+}
+
+/// A type annotation, named after the runtime type `type()` reports.
+/// Annotated values may still be `null`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Char,
+    String,
+    List,
+    Map,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+    Number(String),
+    Str(String),
+    Character(String),
+    Bool(bool),
+    Null,
+    Identifier(String),
+    ModuleMember {
+        module: String,
+        name: String,
+    },
+    List(Vec<Expression>),
+    Map(Vec<(Expression, Expression)>),
+    Range {
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    /// The last `names.len()` arguments are passed by name, after any
+    /// passed by position.
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+        names: Vec<String>,
+    },
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+    },
+    Member {
+        object: Box<Expression>,
+        name: String,
+    },
+    /// A closure. The `(a, b) => a + b` form has a body of a single
+    /// `return`.
+    Lambda {
+        params: Vec<Parameter>,
+        body: Block,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    And,
+    Or,
+    In,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateOperator {
+    Increment,
+    Decrement,
+}
+
+impl BinaryOperator {
+    /// The operator as written in Lyra source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::In => "in",
+            _ => self.sign(),
+        }
+    }
+
+    /// The matching C++ operator.
+    pub fn sign(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::In => unreachable!("`in` is generated as a call to `contains`"),
+        }
+    }
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "bool" => Some(Type::Bool),
+            "char" => Some(Type::Char),
+            "string" => Some(Type::String),
+            "list" => Some(Type::List),
+            "map" => Some(Type::Map),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Char => "char",
+            Type::String => "string",
+            Type::List => "list",
+            Type::Map => "map",
+        }
+    }
+}
+
+impl UpdateOperator {
+    pub fn sign(&self) -> &'static str {
+        match self {
+            UpdateOperator::Increment => "++",
+            UpdateOperator::Decrement => "--",
+        }
+    }
+}
@@ -0,0 +1,703 @@
+use crate::ast::*;
+use crate::symbols::{Signature, Slot, Symbols, bind_arguments};
+use crate::types::{Diagnostic, Span};
+use std::collections::HashMap;
+
+/// Checks values against type annotations and operators against the types
+/// the runtime accepts. A type is only known where it follows from
+/// literals, annotations, constants and the built-in conversions. Anything
+/// else is dynamic and passes every check, so unannotated code keeps
+/// working. `null` fits every annotation, as it does at runtime.
+pub fn check(modules: &[Module], symbols: &Symbols) -> Vec<Diagnostic> {
+    let globals = get_globals(modules);
+    let mut diagnostics = Vec::new();
+
+    for module in modules {
+        let mut checker = Checker {
+            module,
+            symbols,
+            globals: &globals,
+            scopes: Vec::new(),
+            return_type: None,
+            diagnostics: Vec::new(),
+        };
+        checker.check_module();
+        diagnostics.extend(checker.diagnostics);
+    }
+
+    diagnostics
+}
+
+/// The type of every module's variables and constants. A constant without
+/// an annotation gets the type of its value when that is a literal.
+fn get_globals(modules: &[Module]) -> HashMap<(&str, &str), Option<Type>> {
+    let mut globals = HashMap::new();
+
+    for module in modules.iter().filter(|module| module.name != "main") {
+        for statement in &module.statements {
+            let ty = match &statement.kind {
+                StatementKind::Let { name, ty, .. } => (name, *ty),
+                StatementKind::Const { name, ty, value } => (name, ty.or(get_literal_type(value))),
+                _ => continue,
+            };
+            globals.insert((module.name.as_str(), ty.0.as_str()), ty.1);
+        }
+    }
+
+    globals
+}
+
+pub fn get_literal_type(expression: &Expression) -> Option<Type> {
+    match &expression.kind {
+        ExpressionKind::Number(n) if n.contains('.') => Some(Type::Float),
+        ExpressionKind::Number(_) => Some(Type::Int),
+        ExpressionKind::Str(_) => Some(Type::String),
+        ExpressionKind::Character(_) => Some(Type::Char),
+        ExpressionKind::Bool(_) => Some(Type::Bool),
+        ExpressionKind::List(_) => Some(Type::List),
+        ExpressionKind::Map(_) => Some(Type::Map),
+        _ => None,
+    }
+}
+
+/// Whether the runtime stores the type as a number, which is what
+/// arithmetic works on.
+fn is_number(ty: Type) -> bool {
+    matches!(ty, Type::Int | Type::Float | Type::Bool | Type::Char)
+}
+
+struct Checker<'a> {
+    module: &'a Module,
+    symbols: &'a Symbols<'a>,
+    globals: &'a HashMap<(&'a str, &'a str), Option<Type>>,
+    scopes: Vec<HashMap<String, Option<Type>>>,
+    return_type: Option<Type>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn check_module(&mut self) {
+        let name = self.module.name.as_str();
+        self.scopes.push(
+            self.globals
+                .iter()
+                .filter(|((module, _), _)| *module == name)
+                .map(|((_, global), ty)| (global.to_string(), *ty))
+                .collect(),
+        );
+
+        // The variables and constants of `main.ly` are locals of `main()`.
+        let main = name == "main";
+        self.scopes.push(HashMap::new());
+
+        for statement in &self.module.statements {
+            match &statement.kind {
+                StatementKind::Let { ty, value, .. } if !main => {
+                    if let Some(value) = value {
+                        let found = self.check_expression(value);
+                        self.expect_type(*ty, found, &value.span);
+                    }
+                }
+                StatementKind::Const { ty, value, .. } if !main => {
+                    let found = self.check_expression(value);
+                    self.expect_type(*ty, found, &value.span);
+                }
+                StatementKind::Function {
+                    params,
+                    return_type,
+                    body,
+                    ..
+                }
+                | StatementKind::Method {
+                    params,
+                    return_type,
+                    body,
+                    ..
+                } => {
+                    // Functions are generated outside of `main()`, so they
+                    // can't see its locals.
+                    let locals = self.scopes.split_off(1);
+                    self.check_params(params);
+                    self.return_type = *return_type;
+                    self.check_statements(body);
+                    self.return_type = None;
+                    self.scopes.pop();
+                    self.scopes.extend(locals);
+                }
+                _ => self.check_statement(statement),
+            }
+        }
+    }
+
+    /// Opens a scope with the parameters, checking each default against its
+    /// parameter's type with the parameters before it in scope.
+    fn check_params(&mut self, params: &[Parameter]) {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            if let Some(default) = &param.default {
+                let found = self.check_expression(default);
+                self.expect_type(param.ty, found, &default.span);
+            }
+            self.declare(&param.name, param.ty);
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(block);
+        self.scopes.pop();
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let { name, ty, value } => {
+                if let Some(value) = value {
+                    let found = self.check_expression(value);
+                    self.expect_type(*ty, found, &value.span);
+                }
+                self.declare(name, *ty);
+            }
+            StatementKind::Const { name, ty, value } => {
+                let found = self.check_expression(value);
+                self.expect_type(*ty, found, &value.span);
+                self.declare(name, ty.or(found));
+            }
+            StatementKind::Assign {
+                target,
+                operator,
+                value,
+            } => {
+                let target_type = self.check_expression(target);
+                let declared = self.get_declared_type(target);
+                let mut found = self.check_expression(value);
+                if let Some(operator) = operator {
+                    found = self.check_binary(*operator, target_type, found, &statement.span);
+                }
+                self.expect_type(declared, found, &value.span);
+            }
+            StatementKind::Update {
+                target, operator, ..
+            } => {
+                let found = self.check_expression(target);
+                let verb = match operator {
+                    UpdateOperator::Increment => "increment",
+                    UpdateOperator::Decrement => "decrement",
+                };
+
+                match found {
+                    Some(ty) if !is_number(ty) => {
+                        self.error(format!("cannot {verb} a `{}`", ty.name()), &target.span)
+                    }
+                    // The runtime adds or subtracts an int, which turns these
+                    // into ints.
+                    Some(ty @ (Type::Bool | Type::Char))
+                        if self.get_declared_type(target).is_some() =>
+                    {
+                        self.error(
+                            format!("cannot {verb} a `{}`: the result is an `int`", ty.name()),
+                            &target.span,
+                        )
+                    }
+                    _ => {}
+                }
+            }
+            StatementKind::Expression(expression) => {
+                self.check_expression(expression);
+            }
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for (condition, body) in branches {
+                    self.check_expression(condition);
+                    self.check_block(body);
+                }
+                if let Some(body) = else_body {
+                    self.check_block(body);
+                }
+            }
+            StatementKind::Loop { body } => self.check_block(body),
+            StatementKind::While { condition, body } => {
+                self.check_expression(condition);
+                self.check_block(body);
+            }
+            StatementKind::For {
+                variable,
+                value_variable,
+                iterable,
+                body,
+            } => {
+                let iterable_type = self.check_expression(iterable);
+                let element = match (&iterable.kind, iterable_type) {
+                    (ExpressionKind::Range { .. }, _) => Some(Type::Int),
+                    (_, Some(Type::String)) => Some(Type::Char),
+                    (_, Some(ty)) if ty != Type::List && ty != Type::Map => {
+                        self.error(
+                            format!("cannot loop over a `{}`", ty.name()),
+                            &iterable.span,
+                        );
+                        None
+                    }
+                    _ => None,
+                };
+
+                self.scopes.push(HashMap::new());
+                match value_variable {
+                    // Lists and strings pair each element with its index.
+                    Some(value_variable) => {
+                        let index = matches!(iterable_type, Some(Type::List | Type::String))
+                            .then_some(Type::Int);
+                        self.declare(variable, index);
+                        self.declare(value_variable, element);
+                    }
+                    None => self.declare(variable, element),
+                }
+                self.check_statements(body);
+                self.scopes.pop();
+            }
+            StatementKind::Match { value, arms } => {
+                self.check_expression(value);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    if let Pattern::List { items, rest } = &arm.pattern {
+                        for item in items.iter().filter(|&item| item != "_") {
+                            self.declare(item, None);
+                        }
+                        if let Some(rest) = rest.as_ref().filter(|&rest| rest != "_") {
+                            self.declare(rest, Some(Type::List));
+                        }
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard);
+                    }
+                    self.check_statements(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            StatementKind::Try {
+                body,
+                variable,
+                catch_body,
+                finally_body,
+            } => {
+                self.check_block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = variable {
+                        self.declare(variable, None);
+                    }
+                    self.check_statements(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.check_block(finally_body);
+                }
+            }
+            StatementKind::Throw(value) | StatementKind::Yield(value) => {
+                self.check_expression(value);
+            }
+            StatementKind::Break | StatementKind::Continue => {}
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    let found = self.check_expression(value);
+                    self.expect_type(self.return_type, found, &value.span);
+                }
+            }
+            StatementKind::Struct { .. } => {}
+            StatementKind::Function { .. } | StatementKind::Method { .. } => {
+                unreachable!("Definitions only appear at the top level of a file")
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Option<Type> {
+        match &expression.kind {
+            ExpressionKind::Number(_)
+            | ExpressionKind::Str(_)
+            | ExpressionKind::Character(_)
+            | ExpressionKind::Bool(_) => get_literal_type(expression),
+            ExpressionKind::Null => None,
+            ExpressionKind::Identifier(name) => self.lookup(name),
+            ExpressionKind::ModuleMember { module, name } => self
+                .globals
+                .get(&(module.as_str(), name.as_str()))
+                .copied()
+                .flatten(),
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.check_expression(item);
+                }
+                Some(Type::List)
+            }
+            ExpressionKind::Map(entries) => {
+                for (key, value) in entries {
+                    if let Some(ty) = self.check_expression(key)
+                        && (ty == Type::List || ty == Type::Map)
+                    {
+                        self.error(format!("a `{}` can't be a map key", ty.name()), &key.span);
+                    }
+                    self.check_expression(value);
+                }
+                Some(Type::Map)
+            }
+            ExpressionKind::Range { start, end } => {
+                for bound in [start, end].into_iter().flatten() {
+                    if let Some(ty) = self.check_expression(bound)
+                        && ty != Type::Int
+                    {
+                        self.error(
+                            format!("range bounds must be `int`, found `{}`", ty.name()),
+                            &bound.span,
+                        );
+                    }
+                }
+                None
+            }
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+            } => match self.check_expression(operand) {
+                Some(Type::Float) => Some(Type::Float),
+                Some(ty) if is_number(ty) => Some(Type::Int),
+                Some(ty) => {
+                    self.error(format!("cannot negate a `{}`", ty.name()), &expression.span);
+                    None
+                }
+                None => None,
+            },
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => {
+                self.check_expression(operand);
+                Some(Type::Bool)
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.check_expression(left);
+                let right = self.check_expression(right);
+                self.check_binary(*operator, left, right, &expression.span)
+            }
+            ExpressionKind::Call {
+                callee,
+                arguments,
+                names,
+            } => {
+                let types = arguments
+                    .iter()
+                    .map(|argument| self.check_expression(argument))
+                    .collect::<Vec<_>>();
+                self.check_call(callee, arguments, names, &types)
+            }
+            ExpressionKind::Index { object, index } => {
+                let object_type = self.check_expression(object);
+                let index_type = self.check_expression(index);
+
+                if let Some(ty) = object_type
+                    && is_number(ty)
+                {
+                    self.error(format!("cannot index into a `{}`", ty.name()), &object.span);
+                }
+                // String indices look up fields and maps take any key, so
+                // only indices into known lists and strings are checked.
+                if let Some(ty) = index_type
+                    && matches!(object_type, Some(Type::List | Type::String))
+                    && ty != Type::Int
+                    && ty != Type::String
+                {
+                    self.error(
+                        format!("index must be an `int`, found `{}`", ty.name()),
+                        &index.span,
+                    );
+                }
+
+                (object_type == Some(Type::String)).then_some(Type::Char)
+            }
+            ExpressionKind::Member { object, .. } => {
+                self.check_expression(object);
+                None
+            }
+            ExpressionKind::Lambda { params, body } => {
+                self.check_params(params);
+                // A lambda's returns are its own, and aren't annotated.
+                let return_type = self.return_type.take();
+                self.check_statements(body);
+                self.return_type = return_type;
+                self.scopes.pop();
+                None
+            }
+        }
+    }
+
+    /// The result of a binary operator, following the runtime's rules:
+    /// numbers mix into an `int` or a `float`, `+` also joins lists and
+    /// turns anything added to a string into a string, and ordering only
+    /// works between two numbers or two strings.
+    fn check_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: Option<Type>,
+        right: Option<Type>,
+        span: &Span,
+    ) -> Option<Type> {
+        let numeric = match (left, right) {
+            (Some(Type::Float), _) | (_, Some(Type::Float)) => Some(Type::Float),
+            (Some(_), Some(_)) => Some(Type::Int),
+            _ => None,
+        };
+        let is_known = |ty: Option<Type>, check: fn(Type) -> bool| ty.is_some_and(check);
+
+        match operator {
+            BinaryOperator::Add => match (left, right) {
+                (Some(Type::String), _) | (_, Some(Type::String)) => Some(Type::String),
+                (Some(Type::List), Some(Type::List)) => Some(Type::List),
+                (Some(l), Some(r)) if is_number(l) && is_number(r) => numeric,
+                (Some(_), Some(_)) => {
+                    self.operator_error(operator, left, right, span);
+                    None
+                }
+                _ => None,
+            },
+            BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo => {
+                if is_known(left, |ty| !is_number(ty)) || is_known(right, |ty| !is_number(ty)) {
+                    self.operator_error(operator, left, right, span);
+                    return None;
+                }
+                numeric
+            }
+            BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessEqual
+            | BinaryOperator::GreaterEqual => {
+                let mismatched = match (left, right) {
+                    (Some(Type::List), _) | (_, Some(Type::List)) => true,
+                    (Some(l), Some(r)) => is_number(l) != is_number(r),
+                    _ => false,
+                };
+                if mismatched {
+                    self.operator_error(operator, left, right, span);
+                }
+                Some(Type::Bool)
+            }
+            BinaryOperator::In => {
+                let mismatched = match (left, right) {
+                    (Some(l), Some(Type::String)) => l != Type::String && l != Type::Char,
+                    (_, Some(r)) => is_number(r),
+                    _ => false,
+                };
+                if mismatched {
+                    self.operator_error(operator, left, right, span);
+                }
+                Some(Type::Bool)
+            }
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::And
+            | BinaryOperator::Or => Some(Type::Bool),
+        }
+    }
+
+    fn operator_error(
+        &mut self,
+        operator: BinaryOperator,
+        left: Option<Type>,
+        right: Option<Type>,
+        span: &Span,
+    ) {
+        let operands = match (left, right) {
+            (Some(l), Some(r)) => format!("`{}` and `{}`", l.name(), r.name()),
+            (Some(ty), None) | (None, Some(ty)) => format!("a `{}`", ty.name()),
+            (None, None) => unreachable!("Only known types are rejected"),
+        };
+        self.error(
+            format!("cannot use `{}` on {operands}", operator.symbol()),
+            span,
+        );
+    }
+
+    fn check_call(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+        names: &[String],
+        types: &[Option<Type>],
+    ) -> Option<Type> {
+        let symbols = self.symbols;
+
+        match &callee.kind {
+            ExpressionKind::Identifier(name) if self.is_variable(name) => None,
+            ExpressionKind::Identifier(name)
+                if crate::generator::BUILTINS.contains(&name.as_str()) =>
+            {
+                self.check_builtin(name, arguments, types)
+            }
+            ExpressionKind::Identifier(name) => {
+                let function = symbols
+                    .functions
+                    .get(&(self.module.name.as_str(), name.as_str()))?;
+                self.check_arguments(function, 0, arguments, names, types);
+                function.return_type
+            }
+            ExpressionKind::ModuleMember { module, name } => {
+                let function = symbols.functions.get(&(module.as_str(), name.as_str()))?;
+                self.check_arguments(function, 0, arguments, names, types);
+                function.return_type
+            }
+            ExpressionKind::Member { object, name } => {
+                let receiver = self.check_expression(object);
+                let methods = symbols.methods.get(name.as_str())?;
+
+                // Which method runs is only known when a single module
+                // defines one with this name.
+                if let [method] = methods.as_slice() {
+                    if let Some(this) = method.params.first() {
+                        self.expect_type(this.ty, receiver, &object.span);
+                    }
+                    self.check_arguments(method, 1, arguments, names, types);
+                }
+
+                let return_type = methods[0].return_type;
+                methods
+                    .iter()
+                    .all(|method| method.return_type == return_type)
+                    .then_some(return_type)
+                    .flatten()
+            }
+            _ => {
+                self.check_expression(callee);
+                None
+            }
+        }
+    }
+
+    /// Checks the arguments passed to each parameter. Arguments that don't
+    /// fit the parameters are reported by the resolver.
+    fn check_arguments(
+        &mut self,
+        signature: &Signature,
+        skip: usize,
+        arguments: &[Expression],
+        names: &[String],
+        types: &[Option<Type>],
+    ) {
+        let params = signature.params_from(skip);
+        let Ok(slots) = bind_arguments("", params, arguments.len(), names) else {
+            return;
+        };
+
+        for (param, slot) in params.iter().zip(slots) {
+            if let Slot::Argument(i) = slot {
+                self.expect_type(param.ty, types[i], &arguments[i].span);
+            }
+        }
+    }
+
+    fn check_builtin(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        types: &[Option<Type>],
+    ) -> Option<Type> {
+        let argument = types.first().copied().flatten();
+
+        match name {
+            "len" => {
+                if let Some(ty) = argument
+                    && is_number(ty)
+                {
+                    self.error(
+                        format!("`len` expects a `string` or `list`, found `{}`", ty.name()),
+                        &arguments[0].span,
+                    );
+                }
+                Some(Type::Int)
+            }
+            "int" | "float" | "char" => {
+                let result = Type::from_name(name);
+                if let Some(ty) = argument
+                    && !is_number(ty)
+                {
+                    self.error(
+                        format!("cannot convert a `{}` to `{name}`", ty.name()),
+                        &arguments[0].span,
+                    );
+                }
+                result
+            }
+            "bool" | "string" | "list" => Type::from_name(name),
+            "type" => Some(Type::String),
+            _ => None,
+        }
+    }
+
+    /// The annotated type of an assignment target. Elements of lists and
+    /// unannotated variables can hold anything.
+    fn get_declared_type(&self, target: &Expression) -> Option<Type> {
+        match &target.kind {
+            ExpressionKind::Identifier(name) => self.lookup(name),
+            ExpressionKind::ModuleMember { module, name } => self
+                .globals
+                .get(&(module.as_str(), name.as_str()))
+                .copied()
+                .flatten(),
+            _ => None,
+        }
+    }
+
+    fn expect_type(&mut self, expected: Option<Type>, found: Option<Type>, span: &Span) {
+        let (Some(expected), Some(found)) = (expected, found) else {
+            return;
+        };
+
+        if expected != found {
+            let hint = if expected == Type::Float && found == Type::Int {
+                "; use `float()` to convert it"
+            } else {
+                ""
+            };
+            self.error(
+                format!(
+                    "mismatched types: expected `{}`, found `{}`{hint}",
+                    expected.name(),
+                    found.name()
+                ),
+                span,
+            );
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: Option<Type>) {
+        self.scopes
+            .last_mut()
+            .expect("A scope is always open")
+            .insert(name.to_string(), ty);
+    }
+
+    fn is_variable(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name))
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .flatten()
+    }
+
+    fn error(&mut self, message: String, span: &Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+}
@@ -1,25 +1,85 @@
-use crate::types::{Method, Reader, Writer};
+use crate::ast::Module;
+use crate::infer::Natives;
+use crate::resolver::Calls;
+use crate::types::{Diagnostic, Method, Reader, Severity, Span, Writer};
+use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::Write;
 
-pub fn compile(filenames: &Vec<String>, executable_name: &str, release: bool) {
-    let mut readers = get_readers(filenames);
+type Sources = HashMap<String, Vec<String>>;
+
+pub fn compile(filenames: &[String], executable_name: &str, release: bool) {
+    let (mut modules, calls, sources) = analyze(filenames);
+
+    crate::optimizer::optimize(&mut modules);
+
     let mut writers = get_writers(filenames);
 
-    for ((filename, reader), writer) in filenames
+    for (((filename, module), calls), writer) in filenames
         .iter()
-        .zip(readers.iter_mut())
+        .zip(modules.iter())
+        .zip(calls.iter())
         .zip(writers.iter_mut())
     {
-        generate_c_file(filename, reader, writer);
+        // Unboxing only pays off with optimizations on, so debug builds keep
+        // every value dynamic.
+        let natives = if release {
+            crate::infer::infer(module)
+        } else {
+            crate::infer::Natives::default()
+        };
+        generate_c_file(filename, module, &natives, calls, writer);
     }
 
     flush_writers(&mut writers);
     crate::stdlib::write_stdlib();
-    create_executable(filenames, executable_name, release);
+
+    if let Err(stderr) = create_executable(filenames, executable_name, release) {
+        report_gcc_errors(&stderr, &sources);
+    }
+}
+
+/// Checks the project without building it, reporting lints along with any
+/// errors.
+pub fn lint(filenames: &[String]) {
+    analyze(filenames);
+}
+
+/// Parses and checks every file, reporting what was found and exiting if
+/// the program has errors.
+fn analyze(filenames: &[String]) -> (Vec<Module>, Vec<Calls>, Sources) {
+    let levels = crate::lints::Levels::load().unwrap_or_else(|message| {
+        eprintln!("error: {message}");
+        std::process::exit(1);
+    });
+
+    let mut readers = get_readers(filenames);
+    let mut sources = Sources::new();
+    let mut diagnostics = Vec::new();
+
+    let modules = filenames
+        .iter()
+        .zip(readers.iter_mut())
+        .map(|(filename, reader)| parse_file(filename, reader, &mut sources, &mut diagnostics))
+        .collect::<Vec<_>>();
+
+    report_diagnostics(diagnostics, &sources);
+
+    // The program is only checked once every file parses, so a statement
+    // that failed to parse can't leave behind reports of undefined names.
+    let symbols = crate::symbols::get_symbols(&modules);
+    let mut diagnostics = crate::imports::check_imports(&modules);
+    diagnostics.extend(crate::flow::check_flow(&modules));
+    let (resolved, calls) = crate::resolver::resolve(&modules, &symbols);
+    diagnostics.extend(resolved);
+    diagnostics.extend(crate::checker::check(&modules, &symbols));
+    diagnostics.extend(crate::lints::lint(&modules, &levels));
+    report_diagnostics(diagnostics, &sources);
+
+    (modules, calls, sources)
 }
 
-fn get_readers(filenames: &Vec<String>) -> Vec<Reader> {
+fn get_readers(filenames: &[String]) -> Vec<Reader> {
     filenames
         .iter()
         .map(|filename| {
@@ -30,7 +90,7 @@ fn get_readers(filenames: &Vec<String>) -> Vec<Reader> {
         .collect()
 }
 
-fn get_writers(filenames: &Vec<String>) -> Vec<Writer> {
+fn get_writers(filenames: &[String]) -> Vec<Writer> {
     std::fs::create_dir_all(std::path::Path::new("build/src"))
         .expect("Failed to create a directory for the generated C++ files");
     std::fs::create_dir_all(std::path::Path::new("build/include"))
@@ -51,13 +111,84 @@ fn get_writers(filenames: &Vec<String>) -> Vec<Writer> {
         .collect()
 }
 
-fn generate_c_file(filename: &str, reader: &mut Reader, writer: &mut Writer) {
+fn parse_file(
+    filename: &str,
+    reader: &mut Reader,
+    sources: &mut Sources,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Module {
+    let file = format!("src/{filename}");
+    let source = reader
+        .lines()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to read line from input file");
+
+    let (lines, lex_errors) = crate::lexer::get_lines(&source, &file);
+    let (module, parse_errors) = crate::parser::parse(&lines, filename.trim_end_matches(".ly"));
+
+    diagnostics.extend(lex_errors.into_iter().map(Diagnostic::from));
+    diagnostics.extend(parse_errors.into_iter().map(Diagnostic::from));
+    sources.insert(file, source);
+
+    module
+}
+
+/// Prints every diagnostic in source order and exits if any is an error, so
+/// later stages never see a program an earlier one rejected.
+fn report_diagnostics(mut diagnostics: Vec<Diagnostic>, sources: &Sources) {
+    diagnostics.sort_by(|a, b| {
+        (&a.span.file, a.span.line, a.span.column).cmp(&(&b.span.file, b.span.line, b.span.column))
+    });
+
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+        report_source_line(&diagnostic.span, sources);
+
+        for (note, span) in &diagnostic.notes {
+            eprintln!("{}:{}:{}: note: {note}", span.file, span.line, span.column);
+            report_source_line(span, sources);
+        }
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    if errors > 0 {
+        eprintln!(
+            "error: could not compile due to {errors} previous error{}",
+            if errors == 1 { "" } else { "s" }
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Prints the line a span is on with the span underlined.
+fn report_source_line(span: &Span, sources: &Sources) {
+    let line = &sources[&span.file][span.line - 1];
+    let padding: String = line[..span.start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = line[span.start..span.end].chars().count().max(1);
+    let gutter = " ".repeat(span.line.to_string().len());
+
+    eprintln!("{gutter} |");
+    eprintln!("{} | {line}", span.line);
+    eprintln!("{gutter} | {padding}{}", "^".repeat(width));
+    eprintln!();
+}
+
+fn generate_c_file(
+    filename: &str,
+    module: &Module,
+    natives: &Natives,
+    calls: &Calls,
+    writer: &mut Writer,
+) {
     write_includes(filename, writer);
 
-    let mut last_tabs = 0;
-    let mut c_code;
-    let mut h_code;
-    let mut lines = reader.lines().peekable();
     let mut methods = Vec::new();
     let mut header_writer = if filename == "main.ly" {
         None
@@ -66,76 +197,35 @@ fn generate_c_file(filename: &str, reader: &mut Reader, writer: &mut Writer) {
     };
 
     write_header_guard(filename, &mut header_writer);
+    writeln!(
+        writer,
+        "{}",
+        crate::generator::generate_imports(&module.imports)
+    )
+    .expect("Failed to write to output file");
 
-    while let Some(line) = lines.peek() {
-        let line = line
-            .as_ref()
-            .expect("Failed to read line from input file")
-            .clone();
-        let (tokens, _) = crate::lexer::get_tokens(&line);
-
-        if tokens.is_empty() {
-            lines.next();
-            continue;
-        }
-
-        let code = crate::generator::generate_imports(&tokens);
-        if let Some(code) = code {
-            writeln!(writer, "{code}").expect("Failed to write to output file");
-        } else {
-            break;
-        }
-
-        lines.next();
-    }
+    let (c_code, h_code) = crate::generator::generate(module, natives, calls, &mut methods);
 
+    // `main.ly` has no header, so its declarations open the file.
     if filename == "main.ly" {
-        writeln!(writer, "int main() {{").expect("Failed to write to output file");
+        writeln!(writer, "namespace _main {{\n{h_code}\n}}\n")
+            .expect("Failed to write to output file");
     }
 
-    while let Some(line) = lines.next() {
-        let line = line.expect("Failed to read line from input file");
-        let (tokens, mut tabs) = crate::lexer::get_tokens(&line);
-
-        if tokens.is_empty() {
-            tabs = last_tabs;
-        }
-
-        (c_code, h_code) =
-            crate::generator::generate(&tokens, filename, tabs, last_tabs, &mut methods);
-
-        if !c_code.is_empty() {
-            writeln!(writer, "{c_code}").expect("Failed to write to output file");
-        }
-
-        if let Some(h_writer) = &mut header_writer {
-            if !h_code.is_empty() {
-                writeln!(h_writer, "{h_code}").expect("Failed to write to header file");
-            }
-        }
-
-        last_tabs = tabs;
+    if !c_code.is_empty() {
+        writeln!(writer, "{c_code}").expect("Failed to write to output file");
     }
 
-    let scope = (0..last_tabs)
-        .map(|i| {
-            let brace_tabs = last_tabs - i - 1;
-
-            let closing_brace = if filename != "main.ly" && brace_tabs == 0 {
-                "\treturn Value(nullptr);\n}".to_string()
-            } else {
-                "}".to_string()
-            };
-
-            format!("{}{}", "\t".repeat(brace_tabs as usize), closing_brace)
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    if let Some(h_writer) = &mut header_writer
+        && !h_code.is_empty()
+    {
+        writeln!(h_writer, "{h_code}").expect("Failed to write to header file");
+    }
 
-    write!(writer, "{scope}").expect("Failed to write scope end");
     write_header_ending(&mut header_writer);
 
     if filename == "main.ly" {
+        write_ending(writer, "_main", methods);
         write_main_ending(writer);
     } else {
         write_ending(writer, filename.trim_end_matches(".ly"), methods);
@@ -177,7 +267,8 @@ fn write_header_guard(filename: &str, header_writer: &mut Option<Writer>) {
 }
 
 fn write_main_ending(writer: &mut Writer) {
-    write!(writer, "}}").expect("Failed to write main function end");
+    write!(writer, "\n\nint main() {{\n\t_main::_run();\n}}")
+        .expect("Failed to write main function");
 }
 
 fn write_ending(writer: &mut Writer, filename: &str, methods: Vec<Method>) {
@@ -186,16 +277,47 @@ fn write_ending(writer: &mut Writer, filename: &str, methods: Vec<Method>) {
     for method in methods {
         let Method {
             method,
-            num_params,
+            receiver,
             args_str,
+            min_params,
+            max_params,
         } = method;
+        let (condition, expected) = match max_params {
+            Some(max) if max == min_params => (format!("_args.size() != {max}"), max.to_string()),
+            Some(max) if min_params == 0 => {
+                (format!("_args.size() > {max}"), format!("0 to {max}"))
+            }
+            Some(max) => (
+                format!("_args.size() < {min_params} || _args.size() > {max}"),
+                format!("{min_params} to {max}"),
+            ),
+            None => (
+                format!("_args.size() < {min_params}"),
+                format!("at least {min_params}"),
+            ),
+        };
+        let function = crate::generator::method_function(&method, receiver.as_deref());
+        // A struct's methods are registered under the struct's name too.
+        let (name, registered) = match &receiver {
+            Some(receiver) => (
+                format!("{receiver}.{method}"),
+                format!("\"{receiver}\", \"{method}\""),
+            ),
+            None => (method.clone(), format!("\"{method}\"")),
+        };
+        // With a rest parameter and nothing required, any call fits.
+        let check = if max_params.is_none() && min_params == 0 {
+            String::new()
+        } else {
+            format!(
+                "if ({condition})\n                    throw std::runtime_error(\"{name} expects {expected} args\");\n                "
+            )
+        };
         writeln!(
             writer,
             r#"Value::register_method(
-            "{method}", [](const std::vector<Value>& args) -> Value {{
-                if (args.size() != {num_params})
-                    throw std::runtime_error("{method} expects {num_params} args");
-                return {filename}_{method}({args_str});
+            {registered}, [](const std::vector<Value>& _args) -> Value {{
+                {check}return {filename}::{function}({args_str});
             }});"#
         )
         .expect("Failed to write method registration");
@@ -209,13 +331,18 @@ fn write_header_ending(header_writer: &mut Option<Writer>) {
     }
 }
 
-fn flush_writers(writers: &mut Vec<Writer>) {
+fn flush_writers(writers: &mut [Writer]) {
     for writer in writers.iter_mut() {
         writer.flush().expect("Failed to flush output file");
     }
 }
 
-fn create_executable(filenames: &Vec<String>, executable_name: &str, release: bool) {
+/// Runs g++ on the generated files, returning its error output on failure.
+fn create_executable(
+    filenames: &[String],
+    executable_name: &str,
+    release: bool,
+) -> Result<(), String> {
     let c_files = filenames
         .iter()
         .map(|filename| {
@@ -228,8 +355,10 @@ fn create_executable(filenames: &Vec<String>, executable_name: &str, release: bo
         .collect::<Vec<_>>();
 
     let mut cmd = std::process::Command::new("g++");
+    // Plain ASCII quotes keep the messages easy to pick apart.
+    cmd.env("LC_ALL", "C");
     cmd.arg("-Ibuild/include");
-    cmd.args(&["-std=c++17", "-Werror", "-Wall", "-Wextra", "-pedantic"]);
+    cmd.args(["-std=c++20", "-Werror", "-Wall", "-Wextra", "-pedantic"]);
     if release {
         cmd.args([
             "-O3",
@@ -244,9 +373,125 @@ fn create_executable(filenames: &Vec<String>, executable_name: &str, release: bo
         .arg("-o")
         .arg(format!("build/{executable_name}"));
 
-    let status = cmd.status().expect("Failed to run g++");
+    let output = cmd.output().expect("Failed to run g++");
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+/// Reports g++ errors against the Lyra lines the `#line` directives point
+/// at. Anything g++ could not place in a Lyra file is a fault in the
+/// generated code, so it is shown as is.
+fn report_gcc_errors(stderr: &str, sources: &Sources) -> ! {
+    let mut diagnostics = Vec::new();
+
+    for line in stderr.lines() {
+        if let Some(diagnostic) = get_gcc_diagnostic(line, sources)
+            && !diagnostics.iter().any(|seen: &Diagnostic| {
+                seen.span.file == diagnostic.span.file
+                    && seen.span.line == diagnostic.span.line
+                    && seen.message == diagnostic.message
+            })
+        {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    if diagnostics.is_empty() {
+        eprint!("{stderr}");
+        eprintln!("error: g++ failed to compile the generated C++");
+        std::process::exit(1);
+    }
+
+    report_diagnostics(diagnostics, sources);
+    unreachable!("Reporting at least one diagnostic exits")
+}
+
+/// Turns a `file:line:column: error: message` line from g++ into a
+/// diagnostic when it points into a Lyra source file.
+fn get_gcc_diagnostic(line: &str, sources: &Sources) -> Option<Diagnostic> {
+    let mut parts = line.splitn(4, ':');
+    let file = parts.next()?;
+    let line_number = parts.next()?.parse::<usize>().ok()?;
+    parts.next()?.parse::<usize>().ok()?;
+    let message = parts.next()?.trim_start();
+    let message = message
+        .strip_prefix("error: ")
+        .or_else(|| message.strip_prefix("fatal error: "))?;
+
+    let text = sources.get(file)?.get(line_number.checked_sub(1)?)?;
+    let start = text.len() - text.trim_start().len();
+
+    Some(Diagnostic::error(
+        translate_gcc_message(message),
+        &Span {
+            file: file.to_string(),
+            line: line_number,
+            column: text[..start].chars().count() + 1,
+            start,
+            end: text.trim_end().len().max(start),
+        },
+    ))
+}
+
+/// Rewrites the g++ messages a Lyra program can run into in Lyra terms.
+fn translate_gcc_message(message: &str) -> String {
+    let message = match message.rfind(" [-W") {
+        Some(index) => &message[..index],
+        None => message,
+    };
+    let quoted = message
+        .split('\'')
+        .skip(1)
+        .step_by(2)
+        .map(get_lyra_name)
+        .collect::<Vec<_>>();
+    let first = quoted.first().cloned().unwrap_or_default();
+
+    if message.contains("was not declared in this scope") {
+        match quoted.get(1) {
+            Some(suggestion) => {
+                format!("cannot find `{first}` in this scope; did you mean `{suggestion}`?")
+            }
+            None => format!("cannot find `{first}` in this scope"),
+        }
+    } else if message.contains("is not a member of") {
+        format!(
+            "module `{}` has no member `{first}`",
+            quoted.get(1).cloned().unwrap_or_default()
+        )
+    } else if message.contains("has not been declared") {
+        format!("cannot find module `{first}`; is it imported?")
+    } else if message.starts_with("assignment of read-only variable") {
+        format!("cannot assign to constant `{first}`")
+    } else if message.contains("discards qualifiers") {
+        "cannot modify a constant".to_string()
+    } else if message.starts_with("too many arguments to function") {
+        format!("too many arguments in call to `{first}`")
+    } else if message.starts_with("too few arguments to function") {
+        format!("too few arguments in call to `{first}`")
+    } else if message.starts_with("redeclaration of")
+        || message.starts_with("redefinition of")
+        || message.starts_with("conflicting declaration")
+    {
+        format!("`{first}` is already defined")
+    } else if message.starts_with("lvalue required") {
+        "invalid assignment target".to_string()
+    } else {
+        message.replace('\'', "`")
+    }
+}
 
-    if !status.success() {
-        panic!("gcc failed to compile");
+/// Strips the C++ type and parameters from a name quoted by g++, turning
+/// `Value util::add(Value, Value)` into `util::add` and `_len` into `len`.
+fn get_lyra_name(quoted: &str) -> String {
+    let name = quoted.split('(').next().unwrap_or(quoted).trim();
+    let name = name.rsplit(' ').next().unwrap_or(name);
+    match name.strip_prefix('_') {
+        Some(builtin) if crate::generator::BUILTINS.contains(&builtin) => builtin.to_string(),
+        _ => name.to_string(),
     }
 }
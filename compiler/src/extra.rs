@@ -7,6 +7,7 @@ pub fn help() {
     println!("  init                    Create a new lyra.json project file");
     println!("  build [--release|-r]    Compile the project (optional: release mode)");
     println!("  run [--release|-r]      Compile and run the project (optional: release mode)");
+    println!("  lint                    Check the project and report lints");
     println!("  clean                   Remove the build directory");
     println!();
     println!("Package Management:");
@@ -24,12 +25,13 @@ pub fn help() {
     println!("  lyra build                # Build in debug mode");
     println!("  lyra build --release      # Build in release mode");
     println!("  lyra run                  # Build and run in debug mode");
+    println!("  lyra lint                 # Report lints without building");
     println!("  lyra get math             # Install the 'math' package");
     println!("  lyra login user@email.com password123");
     println!("  lyra publish              # Publish current project");
     println!();
     println!("Files:");
-    println!("  lyra.json                 Project configuration file");
+    println!("  lyra.json                 Project configuration and lint levels");
     println!("  lyra.auth                 Authentication token (auto-generated)");
     println!("  packages/                 Downloaded packages directory");
     println!("  build/                    Compiled output directory");
@@ -131,42 +133,17 @@ pub fn remove(package_name: &str) {
         if packages.get(package_name).is_some() {
             packages.as_object_mut().unwrap().remove(package_name);
 
-            // Extract values
-            let name = config
-                .get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("project");
-            let version = config
-                .get("version")
-                .and_then(|v| v.as_str())
-                .unwrap_or("0.0.0");
-            let description = config
-                .get("description")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            let packages_str =
-                serde_json::to_string_pretty(config.get("packages").unwrap()).unwrap();
-
-            let ordered_json = format!(
-                r#"{{
-  "name": "{}",
-  "version": "{}",
-  "description": "{}",
-  "packages": {}
-}}"#,
-                name, version, description, packages_str
-            );
-
-            fs::write(lyra_config_path, ordered_json).expect("Failed to write lyra.json");
-
-            let package_dir = Path::new("packages").join(&package_name);
-            if package_dir.exists() {
-                if let Err(e) = fs::remove_dir_all(&package_dir) {
-                    println!(
-                        "Failed to remove package directory '{}': {}",
-                        package_name, e
-                    );
-                }
+            fs::write(lyra_config_path, get_ordered_config(&config))
+                .expect("Failed to write lyra.json");
+
+            let package_dir = Path::new("packages").join(package_name);
+            if package_dir.exists()
+                && let Err(e) = fs::remove_dir_all(&package_dir)
+            {
+                println!(
+                    "Failed to remove package directory '{}': {}",
+                    package_name, e
+                );
             }
         } else {
             println!("Package '{}' not found in lyra.json", package_name);
@@ -176,6 +153,44 @@ pub fn remove(package_name: &str) {
     }
 }
 
+/// Writes lyra.json back in its usual key order, keeping the lint levels if
+/// the project sets any.
+fn get_ordered_config(config: &serde_json::Value) -> String {
+    let name = config
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("project");
+    let version = config
+        .get("version")
+        .and_then(|v| v.as_str())
+        .unwrap_or("0.0.0");
+    let description = config
+        .get("description")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    // Nested objects are indented to sit under their key.
+    let to_string = |value| {
+        serde_json::to_string_pretty(value)
+            .unwrap()
+            .replace('\n', "\n  ")
+    };
+    let packages_str = to_string(config.get("packages").unwrap());
+    let lints_str = match config.get("lints") {
+        Some(lints) => format!(",\n  \"lints\": {}", to_string(lints)),
+        None => String::new(),
+    };
+
+    format!(
+        r#"{{
+  "name": "{}",
+  "version": "{}",
+  "description": "{}",
+  "packages": {}{}
+}}"#,
+        name, version, description, packages_str, lints_str
+    )
+}
+
 pub fn get(package_name: &str) {
     use std::fs;
     use std::path::Path;
@@ -231,8 +246,8 @@ pub fn get(package_name: &str) {
     };
 
     // Create package directory
-    let package_dir = Path::new("packages").join(&package_name);
-    if let Err(_) = fs::create_dir_all(&package_dir) {
+    let package_dir = Path::new("packages").join(package_name);
+    if fs::create_dir_all(&package_dir).is_err() {
         println!("Failed to create package directory");
         return;
     }
@@ -247,7 +262,7 @@ pub fn get(package_name: &str) {
 
         let file_name = format!("{name}.ly");
 
-        if let Err(_) = fs::write(package_dir.join(&file_name), content) {
+        if fs::write(package_dir.join(&file_name), content).is_err() {
             println!("Failed to write file");
         }
     }
@@ -278,32 +293,7 @@ pub fn get(package_name: &str) {
         );
     }
 
-    // Preserve order when writing back
-    let name = config
-        .get("name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("project");
-    let proj_version = config
-        .get("version")
-        .and_then(|v| v.as_str())
-        .unwrap_or("0.0.0");
-    let description = config
-        .get("description")
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-    let packages_str = serde_json::to_string_pretty(config.get("packages").unwrap()).unwrap();
-
-    let ordered_json = format!(
-        r#"{{
-  "name": "{}",
-  "version": "{}",
-  "description": "{}",
-  "packages": {}
-}}"#,
-        name, proj_version, description, packages_str
-    );
-
-    if let Err(e) = fs::write(lyra_config_path, ordered_json) {
+    if let Err(e) = fs::write(lyra_config_path, get_ordered_config(&config)) {
         println!("Failed to write lyra.json: {}", e);
         return;
     }
@@ -450,15 +440,15 @@ fn collect_source_files() -> Vec<serde_json::Value> {
     if let Ok(entries) = fs::read_dir("src") {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "ly") {
-                if let Some(file_name) = path.file_stem().and_then(|n| n.to_str()) {
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        files.push(serde_json::json!({
-                            "name": file_name,
-                            "content": content
-                        }));
-                    }
-                }
+            if path.is_file()
+                && path.extension().is_some_and(|ext| ext == "ly")
+                && let Some(file_name) = path.file_stem().and_then(|n| n.to_str())
+                && let Ok(content) = fs::read_to_string(&path)
+            {
+                files.push(serde_json::json!({
+                    "name": file_name,
+                    "content": content
+                }));
             }
         }
     } else {
@@ -0,0 +1,413 @@
+use crate::ast::*;
+use crate::types::{Diagnostic, Span};
+
+/// Checks where control flow statements can go: `break` and `continue` only
+/// inside a loop, and `return` only inside a function or method, which
+/// rules out the top level of `main.ly`. Functions and methods that return
+/// a value on some paths but reach their end on others are warned about,
+/// since those paths return `null`. Lambdas are checked like functions, and
+/// can't `break` or `continue` the loops around them. Nothing can leave a
+/// `finally` block, which runs while its `try` is already being left.
+/// A body with a `yield` makes a generator, which can't `return` a value or
+/// declare a return type, since calling it gives the generator.
+pub fn check_flow(modules: &[Module]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for module in modules {
+        for statement in &module.statements {
+            match &statement.kind {
+                StatementKind::Function {
+                    name,
+                    params,
+                    return_type,
+                    body,
+                    ..
+                }
+                | StatementKind::Method {
+                    name,
+                    params,
+                    return_type,
+                    body,
+                    ..
+                } => {
+                    check_defaults(params, &mut diagnostics);
+                    check_block(body, 0, true, &mut diagnostics);
+                    if yields(body) {
+                        if return_type.is_some() {
+                            diagnostics.push(Diagnostic::error(
+                                format!(
+                                    "`{name}` is a generator, so it can't declare a return type"
+                                ),
+                                &statement.span,
+                            ));
+                        }
+                        check_generator(body, &mut diagnostics);
+                    } else {
+                        check_returns(
+                            &format!("`{name}`"),
+                            body,
+                            &statement.span,
+                            &mut diagnostics,
+                        );
+                    }
+                }
+                _ => check_statement(statement, 0, false, &mut diagnostics),
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn check_block(block: &Block, loops: usize, in_function: bool, diagnostics: &mut Vec<Diagnostic>) {
+    for statement in block {
+        check_statement(statement, loops, in_function, diagnostics);
+    }
+}
+
+fn check_statement(
+    statement: &Statement,
+    loops: usize,
+    in_function: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for expression in get_expressions(statement) {
+        check_expression(expression, diagnostics);
+    }
+
+    match &statement.kind {
+        StatementKind::Break | StatementKind::Continue if loops == 0 => {
+            let keyword = if matches!(statement.kind, StatementKind::Break) {
+                "break"
+            } else {
+                "continue"
+            };
+            diagnostics.push(Diagnostic::error(
+                format!("`{keyword}` outside of a loop"),
+                &statement.span,
+            ));
+        }
+        StatementKind::Return(_) if !in_function => {
+            diagnostics.push(Diagnostic::error(
+                "cannot `return` from the top level of main.ly, which is not a function",
+                &statement.span,
+            ));
+        }
+        StatementKind::Yield(_) if !in_function => {
+            diagnostics.push(Diagnostic::error(
+                "cannot `yield` from the top level of main.ly, which is not a function",
+                &statement.span,
+            ));
+        }
+        StatementKind::If {
+            branches,
+            else_body,
+        } => {
+            for (_, body) in branches {
+                check_block(body, loops, in_function, diagnostics);
+            }
+            if let Some(body) = else_body {
+                check_block(body, loops, in_function, diagnostics);
+            }
+        }
+        StatementKind::Loop { body }
+        | StatementKind::While { body, .. }
+        | StatementKind::For { body, .. } => check_block(body, loops + 1, in_function, diagnostics),
+        StatementKind::Match { arms, .. } => {
+            for arm in arms {
+                check_block(&arm.body, loops, in_function, diagnostics);
+            }
+        }
+        StatementKind::Try {
+            body,
+            catch_body,
+            finally_body,
+            ..
+        } => {
+            check_block(body, loops, in_function, diagnostics);
+            if let Some(catch_body) = catch_body {
+                check_block(catch_body, loops, in_function, diagnostics);
+            }
+            if let Some(finally_body) = finally_body {
+                check_finally(finally_body, 0, diagnostics);
+                // What would leave the block is reported above, so the rest
+                // is checked as if it could.
+                check_block(finally_body, 1, true, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports the statements that would leave a `finally` block, which runs
+/// while its `try` is being left and can't send control anywhere else. It
+/// can't `yield` either, since it also runs while a stopped generator is
+/// being unwound.
+fn check_finally(block: &Block, loops: usize, diagnostics: &mut Vec<Diagnostic>) {
+    for statement in block {
+        let keyword = match &statement.kind {
+            StatementKind::Yield(_) => {
+                diagnostics.push(Diagnostic::error(
+                    "a `finally` block can't `yield`",
+                    &statement.span,
+                ));
+                continue;
+            }
+            StatementKind::Break if loops == 0 => "break",
+            StatementKind::Continue if loops == 0 => "continue",
+            StatementKind::Return(_) => "return",
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for body in branches.iter().map(|(_, body)| body).chain(else_body) {
+                    check_finally(body, loops, diagnostics);
+                }
+                continue;
+            }
+            StatementKind::Loop { body }
+            | StatementKind::While { body, .. }
+            | StatementKind::For { body, .. } => {
+                check_finally(body, loops + 1, diagnostics);
+                continue;
+            }
+            StatementKind::Match { arms, .. } => {
+                for arm in arms {
+                    check_finally(&arm.body, loops, diagnostics);
+                }
+                continue;
+            }
+            StatementKind::Try {
+                body,
+                catch_body,
+                finally_body,
+                ..
+            } => {
+                for body in std::iter::once(body).chain(catch_body).chain(finally_body) {
+                    check_finally(body, loops, diagnostics);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        diagnostics.push(Diagnostic::error(
+            format!("`{keyword}` can't leave a `finally` block"),
+            &statement.span,
+        ));
+    }
+}
+
+/// Checks the lambdas in an expression, whose bodies start outside of any
+/// loop.
+fn check_expression(expression: &Expression, diagnostics: &mut Vec<Diagnostic>) {
+    match &expression.kind {
+        ExpressionKind::Lambda { params, body } => {
+            check_defaults(params, diagnostics);
+            check_block(body, 0, true, diagnostics);
+            if yields(body) {
+                check_generator(body, diagnostics);
+            } else {
+                check_returns("this lambda", body, &expression.span, diagnostics);
+            }
+        }
+        ExpressionKind::List(items) => {
+            for item in items {
+                check_expression(item, diagnostics);
+            }
+        }
+        ExpressionKind::Map(entries) => {
+            for (key, value) in entries {
+                check_expression(key, diagnostics);
+                check_expression(value, diagnostics);
+            }
+        }
+        ExpressionKind::Range { start, end } => {
+            for bound in [start, end].into_iter().flatten() {
+                check_expression(bound, diagnostics);
+            }
+        }
+        ExpressionKind::Unary { operand, .. } => check_expression(operand, diagnostics),
+        ExpressionKind::Binary { left, right, .. } => {
+            check_expression(left, diagnostics);
+            check_expression(right, diagnostics);
+        }
+        ExpressionKind::Call {
+            callee, arguments, ..
+        } => {
+            check_expression(callee, diagnostics);
+            for argument in arguments {
+                check_expression(argument, diagnostics);
+            }
+        }
+        ExpressionKind::Index { object, index } => {
+            check_expression(object, diagnostics);
+            check_expression(index, diagnostics);
+        }
+        ExpressionKind::Member { object, .. } => check_expression(object, diagnostics),
+        ExpressionKind::Number(_)
+        | ExpressionKind::Str(_)
+        | ExpressionKind::Character(_)
+        | ExpressionKind::Bool(_)
+        | ExpressionKind::Null
+        | ExpressionKind::Identifier(_)
+        | ExpressionKind::ModuleMember { .. } => {}
+    }
+}
+
+fn check_defaults(params: &[Parameter], diagnostics: &mut Vec<Diagnostic>) {
+    for default in params.iter().filter_map(|param| param.default.as_ref()) {
+        check_expression(default, diagnostics);
+    }
+}
+
+/// The expressions a statement holds itself, leaving out those in its
+/// bodies.
+fn get_expressions(statement: &Statement) -> Vec<&Expression> {
+    match &statement.kind {
+        StatementKind::Let { value, .. } | StatementKind::Return(value) => value.iter().collect(),
+        StatementKind::Const { value, .. } | StatementKind::Expression(value) => vec![value],
+        StatementKind::Assign { target, value, .. } => vec![target, value],
+        StatementKind::Update { target, .. } => vec![target],
+        StatementKind::If { branches, .. } => {
+            branches.iter().map(|(condition, _)| condition).collect()
+        }
+        StatementKind::While { condition, .. } => vec![condition],
+        StatementKind::For { iterable, .. } => vec![iterable],
+        StatementKind::Throw(value) | StatementKind::Yield(value) => vec![value],
+        StatementKind::Match { value, arms } => std::iter::once(value)
+            .chain(arms.iter().filter_map(|arm| arm.guard.as_ref()))
+            .collect(),
+        StatementKind::Loop { .. }
+        | StatementKind::Break
+        | StatementKind::Continue
+        | StatementKind::Function { .. }
+        | StatementKind::Method { .. }
+        | StatementKind::Try { .. }
+        | StatementKind::Struct { .. } => Vec::new(),
+    }
+}
+
+/// Reports the returns in a generator that give a value, which would have
+/// nowhere to go.
+fn check_generator(body: &Block, diagnostics: &mut Vec<Diagnostic>) {
+    let mut returns = Vec::new();
+    get_returns(body, &mut returns);
+
+    for (_, span) in returns.into_iter().filter(|(has_value, _)| *has_value) {
+        diagnostics.push(Diagnostic::error(
+            "a generator can't `return` a value, only `yield` one",
+            span,
+        ));
+    }
+}
+
+/// Warns when a function, method or lambda returns a value on some paths but not
+/// on others, either with a bare `return` or by reaching its end.
+fn check_returns(subject: &str, body: &Block, span: &Span, diagnostics: &mut Vec<Diagnostic>) {
+    let mut returns = Vec::new();
+    get_returns(body, &mut returns);
+
+    let Some(value) = returns.iter().find(|(has_value, _)| *has_value) else {
+        return;
+    };
+    let (note, end) = match returns.iter().find(|(has_value, _)| !has_value) {
+        Some((_, span)) => ("this `return` has no value", *span),
+        None if always_exits(body) => return,
+        None => (
+            "the end of the body can be reached without a `return`",
+            &body.last().expect("A body has at least one statement").span,
+        ),
+    };
+
+    let diagnostic = Diagnostic::warning(
+        format!("{subject} returns a value on some paths but `null` on others"),
+        span,
+    )
+    .with_note("a value is returned here", value.1)
+    .with_note(note, end);
+    diagnostics.push(diagnostic);
+}
+
+/// Collects every `return` in a block, with whether it gives a value.
+fn get_returns<'a>(block: &'a Block, returns: &mut Vec<(bool, &'a Span)>) {
+    for statement in block {
+        match &statement.kind {
+            StatementKind::Return(value) => returns.push((value.is_some(), &statement.span)),
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for (_, body) in branches {
+                    get_returns(body, returns);
+                }
+                if let Some(body) = else_body {
+                    get_returns(body, returns);
+                }
+            }
+            StatementKind::Loop { body }
+            | StatementKind::While { body, .. }
+            | StatementKind::For { body, .. } => get_returns(body, returns),
+            StatementKind::Match { arms, .. } => {
+                for arm in arms {
+                    get_returns(&arm.body, returns);
+                }
+            }
+            // A `finally` can't `return`.
+            StatementKind::Try {
+                body, catch_body, ..
+            } => {
+                get_returns(body, returns);
+                if let Some(catch_body) = catch_body {
+                    get_returns(catch_body, returns);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether running a block always ends in a `return`, or in a `loop` that
+/// never stops, so control can't reach the statement after it.
+fn always_exits(block: &Block) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        StatementKind::Return(_) | StatementKind::Throw(_) => true,
+        StatementKind::If {
+            branches,
+            else_body: Some(else_body),
+        } => branches.iter().all(|(_, body)| always_exits(body)) && always_exits(else_body),
+        StatementKind::Loop { body } => !breaks(body),
+        // Only a `_` arm without a guard makes sure some arm runs.
+        StatementKind::Match { arms, .. } => {
+            arms.iter().all(|arm| always_exits(&arm.body))
+                && arms
+                    .iter()
+                    .any(|arm| arm.guard.is_none() && matches!(arm.pattern, Pattern::Wildcard))
+        }
+        // Control only carries on past the `try` if a `catch` that doesn't
+        // exit handles what the body throws.
+        StatementKind::Try {
+            body, catch_body, ..
+        } => always_exits(body) && catch_body.as_ref().is_none_or(always_exits),
+        _ => false,
+    })
+}
+
+/// Whether a block contains a `break` out of the loop it is the body of,
+/// leaving out those in loops nested inside it.
+fn breaks(block: &Block) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        StatementKind::Break => true,
+        StatementKind::If {
+            branches,
+            else_body,
+        } => {
+            branches.iter().any(|(_, body)| breaks(body)) || else_body.as_ref().is_some_and(breaks)
+        }
+        StatementKind::Match { arms, .. } => arms.iter().any(|arm| breaks(&arm.body)),
+        StatementKind::Try {
+            body, catch_body, ..
+        } => breaks(body) || catch_body.as_ref().is_some_and(breaks),
+        _ => false,
+    })
+}
@@ -1,512 +1,1128 @@
-use crate::types::Keyword::*;
-use crate::types::Literal::*;
-use crate::types::Method;
-use crate::types::SpecialCharacter;
-use crate::types::SpecialCharacter::*;
-use crate::types::Token;
-use crate::types::Token::*;
-
-pub fn generate_imports(tokens: &[Token]) -> Option<String> {
-    if let [Keyword(Import), Identifier(file)] = tokens {
-        if file == "std" || file == "main" {
-            panic!("Cannot import reserved module name: {}", file);
-        }
-        Some(format!("#include \"{file}.hpp\""))
-    } else {
-        None
-    }
+use crate::ast::*;
+use crate::infer::{Natives, position};
+use crate::resolver::{Calls, call_key};
+use crate::symbols::Slot;
+use crate::types::{Method, Span};
+use std::cell::Cell;
+
+pub const BUILTINS: [&str; 11] = [
+    "print", "type", "len", "exit", "null", "int", "float", "bool", "char", "string", "list",
+];
+
+/// Names the generated C++ declares next to Lyra's own locals, which Lyra
+/// code can't declare.
+pub const RESERVED: [&str; 2] = ["_args", "_thrown"];
+
+pub fn generate_imports(imports: &[Import]) -> String {
+    imports
+        .iter()
+        .map(|import| format!("#include \"{}.hpp\"\n", import.module))
+        .collect()
 }
 
+/// Generates the C++ for a module. Locals in `natives` are stored as native
+/// C++ values, and boxed into `Value`s wherever they meet dynamic code.
+/// Calls in `calls` pass their arguments in the order of the parameters.
 pub fn generate(
-    tokens: &Vec<Token>,
-    filename: &str,
-    tabs: u8,
-    last_tabs: u8,
+    module: &Module,
+    natives: &Natives,
+    calls: &Calls,
     methods: &mut Vec<Method>,
 ) -> (String, String) {
-    let scope = if tabs < last_tabs {
-        (0..last_tabs - tabs)
-            .map(|i| {
-                let brace_tabs = last_tabs - i - 1;
+    Generator {
+        natives,
+        calls,
+        tabs: Cell::new(0),
+        in_generator: Cell::new(false),
+    }
+    .generate_module(module, methods)
+}
 
-                let closing_brace = if filename != "main.ly" && brace_tabs == 0 {
-                    "\treturn Value(nullptr);\n}".to_string()
-                } else {
-                    "}".to_string()
-                };
+struct Generator<'a> {
+    natives: &'a Natives,
+    calls: &'a Calls,
+    /// The indentation of the statement being generated, which lambdas in
+    /// it indent their bodies past. It is 0 only at namespace scope.
+    tabs: Cell<usize>,
+    /// Whether the statements being generated are a generator's body, where
+    /// a `return` ends its coroutine.
+    in_generator: Cell<bool>,
+}
 
-                format!("{}{}", "\t".repeat(brace_tabs as usize), closing_brace)
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-            + "\n"
-    } else {
-        "".to_string()
-    };
-
-    if filename != "main" && tabs == 0 {
-        if let [
-            Keyword(Method),
-            Identifier(method),
-            SpecialCharacter(OpenParenthesis),
-            params @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] = tokens.as_slice()
-        {
-            let params_str = generate_params(params);
-            let num_params = if params_str.is_empty() {
-                0
-            } else {
-                params_str.matches(',').count() + 1
-            };
-            let args_str = generate_args(num_params);
+impl Generator<'_> {
+    /// Generates a module's definitions in its namespace, with their
+    /// declarations for its header. The definitions of `main.ly` go in the
+    /// `_main` namespace, declared at the top of the file so they can be
+    /// used before they appear, and the rest of it becomes `_main::_run()`,
+    /// which `main()` calls.
+    fn generate_module(&self, module: &Module, methods: &mut Vec<Method>) -> (String, String) {
+        let main = module.name == "main";
+        let name = if main { "_main" } else { module.name.as_str() };
+        let mut c_code = Vec::new();
+        let mut h_code = Vec::new();
+        let mut statements = Vec::new();
+
+        for statement in &module.statements {
+            let is_definition = matches!(
+                statement.kind,
+                StatementKind::Function { .. }
+                    | StatementKind::Method { .. }
+                    | StatementKind::Struct { .. }
+            );
+            if main && !is_definition {
+                statements.push(self.generate_statement(statement, 1));
+                continue;
+            }
+
+            match &statement.kind {
+                StatementKind::Let {
+                    name: var, value, ..
+                } => {
+                    c_code.push(format!(
+                        "{}Value {name}::{var}({});",
+                        line_directive(&statement.span),
+                        self.generate_optional(value.as_ref())
+                    ));
+                    h_code.push(format!(
+                        "{}extern Value {var};",
+                        line_directive(&statement.span)
+                    ));
+                }
+                StatementKind::Const {
+                    name: var, value, ..
+                } => {
+                    c_code.push(format!(
+                        "{}const Value {name}::{var}({});",
+                        line_directive(&statement.span),
+                        self.generate_expression(value)
+                    ));
+                    h_code.push(format!(
+                        "{}extern const Value {var};",
+                        line_directive(&statement.span)
+                    ));
+                }
+                StatementKind::Function {
+                    name: function,
+                    params,
+                    body,
+                    ..
+                } => {
+                    let params_code = generate_params(params);
+                    c_code.push(format!(
+                        "{}Value {name}::{function}({params_code}) {{\n{}\n}}",
+                        line_directive(&statement.span),
+                        self.generate_function_body(params, body)
+                    ));
+                    h_code.push(format!(
+                        "{}Value {function}({params_code});",
+                        line_directive(&statement.span)
+                    ));
+                }
+                StatementKind::Method {
+                    name: method,
+                    receiver,
+                    params,
+                    body,
+                    ..
+                } => {
+                    let params_code = generate_params(params);
+                    let function = method_function(method, receiver.as_deref());
+                    c_code.push(format!(
+                        "{}Value {name}::{function}({params_code}) {{\n{}\n}}",
+                        line_directive(&statement.span),
+                        self.generate_function_body(params, body)
+                    ));
+                    h_code.push(format!(
+                        "{}Value {function}({params_code});",
+                        line_directive(&statement.span)
+                    ));
+                    let rest = params.iter().any(|param| param.rest);
+                    methods.push(Method {
+                        method: method.to_string(),
+                        receiver: receiver.clone(),
+                        args_str: generate_args(params),
+                        min_params: params
+                            .iter()
+                            .filter(|param| param.default.is_none() && !param.rest)
+                            .count(),
+                        max_params: (!rest).then_some(params.len()),
+                    });
+                }
+                StatementKind::Struct {
+                    name: structure,
+                    fields,
+                } => {
+                    let params = generate_params(fields);
+                    c_code.push(format!(
+                        "{}Value {name}::{structure}({params}) {{\n{}\n}}",
+                        line_directive(&statement.span),
+                        generate_constructor(structure, fields, 1)
+                    ));
+                    h_code.push(format!(
+                        "{}Value {structure}({params});",
+                        line_directive(&statement.span)
+                    ));
+                }
+                _ => c_code.push(self.generate_statement(statement, 0)),
+            }
+        }
 
-            methods.push(Method {
-                method: method.to_string(),
-                num_params,
-                args_str,
-            });
+        if main {
+            c_code.push(format!(
+                "void _main::_run() {{\n{}\n}}",
+                statements.join("\n")
+            ));
+            h_code.push("void _run();".to_string());
         }
+
+        (c_code.join("\n\n"), h_code.join("\n"))
     }
 
-    (
-        format!("{}{}", scope, match_c_code(tokens, filename, tabs)),
-        if filename == "main.ly" || tabs > 0 {
-            "".to_string()
+    fn generate_function_body(&self, params: &[Parameter], body: &Block) -> String {
+        let mut code = self.generate_defaults(params, 1);
+        if yields(body) {
+            code.push(format!("\treturn {};", self.generate_generator(body, 1)));
         } else {
-            match_h_code(tokens)
-        },
-    )
-}
+            code.push(self.generate_block(body, 1));
+            code.push("\treturn Value(nullptr);".to_string());
+        }
+        code.retain(|line| !line.is_empty());
+        code.join("\n")
+    }
 
-fn match_c_code(tokens: &Vec<Token>, mut filename: &str, tabs: u8) -> String {
-    filename = filename.trim_end_matches(".ly");
+    /// Generates the generator a body with a `yield` gives when called. The
+    /// body becomes a coroutine the runtime resumes a step at a time,
+    /// holding copies of the parameters it can assign to.
+    fn generate_generator(&self, body: &Block, tabs: usize) -> String {
+        let indent = "\t".repeat(tabs);
+        let outer = self.in_generator.replace(true);
+        let code = self.generate_block(body, tabs + 1);
+        self.in_generator.set(outer);
+        format!(
+            "_generator([=]() mutable -> GeneratorBody {{\n{code}\n{indent}\tco_return;\n{indent}}})"
+        )
+    }
 
-    let code = match tokens.as_slice() {
-        [] => "".to_string(),
+    /// Gives the parameters with defaults their defaults when their
+    /// arguments are missing or `null`.
+    fn generate_defaults(&self, params: &[Parameter], tabs: usize) -> Vec<String> {
+        let indent = "\t".repeat(tabs);
+        let outer = self.tabs.replace(tabs);
+        let code = params
+            .iter()
+            .filter_map(|param| {
+                let default = param.default.as_ref()?;
+                Some(format!(
+                    "{indent}if ({0}.get_type() == Type::Null) {0} = {1};",
+                    param.name,
+                    self.generate_expression(default)
+                ))
+            })
+            .collect();
+        self.tabs.set(outer);
+        code
+    }
 
-        [Keyword(Break)] => "break;".to_string(),
-        [Keyword(Continue)] => "continue;".to_string(),
+    fn generate_block(&self, block: &Block, tabs: usize) -> String {
+        block
+            .iter()
+            .map(|statement| self.generate_statement(statement, tabs))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-        [Keyword(Return), expression @ ..] if filename != "main" => {
-            let return_value = if expression.is_empty() {
-                "Value(nullptr)".to_string()
-            } else {
-                generate_expression(expression)
-            };
+    fn generate_statement(&self, statement: &Statement, tabs: usize) -> String {
+        let indent = "\t".repeat(tabs);
+        let outer = self.tabs.replace(tabs);
 
-            format!("return {};", return_value)
-        }
+        let code = match self.generate_native_statement(statement, tabs) {
+            Some(code) => code,
+            None => match &statement.kind {
+                StatementKind::Let { name, value, .. } => {
+                    format!("Value {name}({});", self.generate_optional(value.as_ref()))
+                }
+                StatementKind::Const { name, value, .. } => {
+                    format!("const Value {name}({});", self.generate_expression(value))
+                }
+                StatementKind::Assign {
+                    target,
+                    operator,
+                    value,
+                } => format!(
+                    "{} {}= {};",
+                    self.generate_target(target),
+                    operator.map_or("", |operator| operator.sign()),
+                    self.generate_expression(value)
+                ),
+                StatementKind::Update {
+                    target,
+                    operator,
+                    prefix: true,
+                } => format!("{}{};", operator.sign(), self.generate_target(target)),
+                StatementKind::Update {
+                    target,
+                    operator,
+                    prefix: false,
+                } => format!("{}{};", self.generate_target(target), operator.sign()),
+                StatementKind::Expression(expression) => {
+                    format!("{};", self.generate_expression(expression))
+                }
+                StatementKind::If {
+                    branches,
+                    else_body,
+                } => {
+                    let mut code = String::new();
 
-        [
-            Keyword(Method),
-            Identifier(method),
-            SpecialCharacter(OpenParenthesis),
-            params @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] if filename != "main" && tabs == 0 => {
-            format!("Value {filename}_{method}({}) {{", generate_params(params))
-        }
+                    for (i, (condition, body)) in branches.iter().enumerate() {
+                        if i > 0 {
+                            code.push_str(&format!(
+                                "\n{}{indent}else ",
+                                line_directive(&condition.span)
+                            ));
+                        }
+                        code.push_str(&format!(
+                            "if ({}) {{\n{}\n{indent}}}",
+                            self.generate_condition(condition),
+                            self.generate_block(body, tabs + 1)
+                        ));
+                    }
 
-        [
-            Identifier(function),
-            SpecialCharacter(OpenParenthesis),
-            params @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] if filename != "main" && tabs == 0 => {
-            format!(
-                "Value {filename}::{function}({}) {{",
-                generate_params(params)
-            )
-        }
+                    // An `if` the optimizer cut down to its `else` is just a
+                    // block.
+                    if let Some(body) = else_body {
+                        code.push_str(&format!(
+                            "{}{{\n{}\n{indent}}}",
+                            if branches.is_empty() { "" } else { " else " },
+                            self.generate_block(body, tabs + 1)
+                        ));
+                    }
 
-        [
-            Identifier(file),
-            SpecialCharacter(Colon),
-            SpecialCharacter(Colon),
-            Identifier(function),
-            SpecialCharacter(OpenParenthesis),
-            args @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] => {
-            let arguments = if args.is_empty() {
-                "".to_string()
-            } else {
-                generate_expression(args)
-            };
+                    code
+                }
+                StatementKind::Loop { body } => format!(
+                    "while (true) {{\n{}\n{indent}}}",
+                    self.generate_block(body, tabs + 1)
+                ),
+                StatementKind::While { condition, body } => format!(
+                    "while ({}) {{\n{}\n{indent}}}",
+                    self.generate_condition(condition),
+                    self.generate_block(body, tabs + 1)
+                ),
+                StatementKind::For {
+                    variable,
+                    value_variable: None,
+                    iterable:
+                        iterable @ Expression {
+                            kind: ExpressionKind::Range { .. },
+                            ..
+                        },
+                    body,
+                } => format!(
+                    "for (const Value& {variable} : {}) {{\n{}\n{indent}}}",
+                    self.generate_expression(iterable),
+                    self.generate_block(body, tabs + 1)
+                ),
+                // Anything else is walked by the runtime's `Iterator`, which
+                // also steps through lazy sequences.
+                StatementKind::For {
+                    variable,
+                    value_variable: None,
+                    iterable,
+                    body,
+                } => format!(
+                    "for (Iterator _iterator({}); _iterator.next();) {{\n{indent}\t[[maybe_unused]] const Value& {variable} = _iterator.item();\n{}\n{indent}}}",
+                    self.generate_expression(iterable),
+                    self.generate_block(body, tabs + 1)
+                ),
+                StatementKind::For {
+                    variable,
+                    value_variable: Some(value_variable),
+                    iterable,
+                    body,
+                } => format!(
+                    "for (Iterator _iterator({}); _iterator.next();) {{\n{indent}\t[[maybe_unused]] const Value& {variable} = _iterator.key();\n{indent}\t[[maybe_unused]] const Value& {value_variable} = _iterator.value();\n{}\n{indent}}}",
+                    self.generate_expression(iterable),
+                    self.generate_block(body, tabs + 1)
+                ),
+                StatementKind::Match { value, arms } => self.generate_match(value, arms, tabs),
+                StatementKind::Try {
+                    body,
+                    variable,
+                    catch_body,
+                    finally_body,
+                } => self.generate_try(
+                    body,
+                    variable.as_deref(),
+                    catch_body.as_ref(),
+                    finally_body.as_ref(),
+                    tabs,
+                ),
+                StatementKind::Throw(value) => {
+                    format!("throw Thrown({});", self.generate_expression(value))
+                }
+                StatementKind::Yield(value) => {
+                    format!("co_yield {};", self.generate_expression(value))
+                }
+                StatementKind::Break => "break;".to_string(),
+                StatementKind::Continue => "continue;".to_string(),
+                StatementKind::Return(_) if self.in_generator.get() => "co_return;".to_string(),
+                StatementKind::Return(value) => {
+                    format!("return {};", self.generate_optional(value.as_ref()))
+                }
+                StatementKind::Function { .. }
+                | StatementKind::Method { .. }
+                | StatementKind::Struct { .. } => {
+                    unreachable!("Definitions are only generated at the top level of a file")
+                }
+            },
+        };
 
-            format!("{file}::{function}({});", arguments)
-        }
+        self.tabs.set(outer);
+        format!("{}{indent}{code}", line_directive(&statement.span))
+    }
 
-        [
-            Identifier(object),
-            SpecialCharacter(Dot),
-            Identifier(method),
-            SpecialCharacter(OpenParenthesis),
-            args @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] => {
-            let arguments = if args.is_empty() {
-                "".to_string()
-            } else {
-                generate_expression(args)
-            };
+    /// Generates a statement that declares or assigns a native local, or
+    /// returns `None` if the statement has nothing to unbox.
+    fn generate_native_statement(&self, statement: &Statement, tabs: usize) -> Option<String> {
+        let native = self
+            .natives
+            .declarations
+            .get(&position(&statement.span))
+            .copied();
 
-            format!("{object}[\"{method}\"]({});", arguments)
+        let code = match (&statement.kind, native) {
+            (
+                StatementKind::Let {
+                    name,
+                    value: Some(value),
+                    ..
+                },
+                Some(ty),
+            ) => format!(
+                // Unused natives would trip g++'s warnings, unlike `Value`s.
+                "[[maybe_unused]] {} {name} = {};",
+                native_name(ty),
+                self.generate_native(value)
+            ),
+            (StatementKind::Const { name, value, .. }, Some(ty)) => format!(
+                "[[maybe_unused]] const {} {name} = {};",
+                native_name(ty),
+                self.generate_native(value)
+            ),
+            (
+                StatementKind::For {
+                    variable,
+                    value_variable: None,
+                    iterable:
+                        Expression {
+                            kind: ExpressionKind::Range { start, end },
+                            ..
+                        },
+                    body,
+                },
+                Some(_),
+            ) => format!(
+                "for (int32_t {variable} = {}, _end_{variable} = {}; {variable} < _end_{variable}; ++{variable}) {{\n{}\n{}}}",
+                start
+                    .as_ref()
+                    .map_or("0".to_string(), |start| self.generate_native(start)),
+                end.as_ref()
+                    .map_or("2147483647".to_string(), |end| self.generate_native(end)),
+                self.generate_block(body, tabs + 1),
+                "\t".repeat(tabs)
+            ),
+            (
+                StatementKind::Update {
+                    target, operator, ..
+                },
+                _,
+            ) if self.get_native_type(target) == Some(Type::Int)
+                && self.is_native_local(target) =>
+            {
+                format!(
+                    "{0} = _i{1}({0}, 1);",
+                    self.generate_native(target),
+                    match operator {
+                        UpdateOperator::Increment => "add",
+                        UpdateOperator::Decrement => "sub",
+                    }
+                )
+            }
+            (
+                StatementKind::Assign {
+                    target,
+                    operator,
+                    value,
+                },
+                _,
+            ) if self.is_native_local(target) => format!(
+                "{} = {};",
+                self.generate_native(target),
+                match operator {
+                    Some(operator) => self.generate_native_binary(*operator, target, value),
+                    None => self.generate_native(value),
+                }
+            ),
+            _ => return None,
+        };
+
+        Some(code)
+    }
+
+    fn generate_optional(&self, expression: Option<&Expression>) -> String {
+        match expression {
+            Some(expression) => self.generate_expression(expression),
+            None => "Value(nullptr)".to_string(),
         }
+    }
 
-        [
-            Identifier(function),
-            SpecialCharacter(OpenParenthesis),
-            args @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] if function == "print" || function == "exit" => {
-            let arguments = if args.is_empty() {
-                "".to_string()
-            } else {
-                generate_expression(args)
-            };
+    /// Generates a `try`. Its `finally` is run by a guard's destructor, so
+    /// it runs however the block is left, and its `catch` catches every
+    /// error, both thrown and from the runtime.
+    fn generate_try(
+        &self,
+        body: &Block,
+        variable: Option<&str>,
+        catch_body: Option<&Block>,
+        finally_body: Option<&Block>,
+        tabs: usize,
+    ) -> String {
+        let indent = "\t".repeat(tabs + 1);
+        let mut code = vec!["{".to_string()];
 
-            format!("_{function}({arguments});")
+        if let Some(finally_body) = finally_body {
+            code.push(format!(
+                "{indent}[[maybe_unused]] const Finally _finally([&]() {{\n{}\n{indent}}});",
+                self.generate_block(finally_body, tabs + 2)
+            ));
         }
 
-        [
-            SpecialCharacter(Plus),
-            SpecialCharacter(Plus),
-            Identifier(var),
-        ] => format!("++{var};"),
-
-        [
-            Identifier(var),
-            SpecialCharacter(Plus),
-            SpecialCharacter(Plus),
-        ] => format!("{var}++;"),
-
-        [
-            SpecialCharacter(Minus),
-            SpecialCharacter(Minus),
-            Identifier(var),
-        ] => format!("--{var};"),
-
-        [
-            Identifier(var),
-            SpecialCharacter(Minus),
-            SpecialCharacter(Minus),
-        ] => format!("{var}--;"),
-
-        [Keyword(Let), Identifier(var)] if filename != "main" && tabs == 0 => {
-            format!("Value {filename}::{var}(nullptr);")
+        // The catch block runs after the handler, where a generator can
+        // still `yield`.
+        match catch_body {
+            Some(catch_body) => {
+                code.push(format!(
+                    "{indent}std::optional<Value> _thrown;\n{indent}try {{\n{}\n{indent}}} catch (const std::exception& _error) {{\n{indent}\t_thrown = _caught(_error);\n{indent}}}\n{indent}if (_thrown) {{",
+                    self.generate_block(body, tabs + 2)
+                ));
+                if let Some(variable) = variable {
+                    code.push(format!(
+                        "{indent}\t[[maybe_unused]] Value {variable} = *_thrown;"
+                    ));
+                }
+                code.push(self.generate_block(catch_body, tabs + 2));
+                code.push(format!("{indent}}}"));
+            }
+            None => code.push(self.generate_block(body, tabs + 1)),
         }
 
-        [Keyword(Let), Identifier(var)] => format!("Value {var}(nullptr);"),
+        code.push(format!("{}}}", "\t".repeat(tabs)));
+        code.join("\n")
+    }
 
-        [
-            Keyword(Let),
-            Identifier(var),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] if filename != "main" && tabs == 0 => {
+    /// Generates a `match` as an `if` per arm, testing a copy of the value.
+    /// An arm binds its names before its guard is tested, so arms can't
+    /// be chained with `else`, and `_matched` skips the rest once one runs.
+    fn generate_match(&self, value: &Expression, arms: &[Arm], tabs: usize) -> String {
+        let indent = "\t".repeat(tabs + 1);
+        let mut code = vec![
+            "{".to_string(),
             format!(
-                "Value {filename}::{var}({});",
-                generate_expression(expression)
-            )
-        }
+                "{indent}const Value _match = {};",
+                self.generate_expression(value)
+            ),
+            format!("{indent}bool _matched = false;"),
+        ];
 
-        [
-            Keyword(Let),
-            Identifier(var),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] => {
-            format!("Value {var}({});", generate_expression(expression))
-        }
+        for arm in arms {
+            let test = match &arm.pattern {
+                Pattern::Wildcard => None,
+                Pattern::Literal(literal) => Some(format!(
+                    "_match.matches_literal({})",
+                    self.generate_expression(literal)
+                )),
+                Pattern::Range { start, end } => Some(format!(
+                    "_match.matches_range({}, {})",
+                    self.generate_optional(start.as_ref()),
+                    self.generate_optional(end.as_ref())
+                )),
+                Pattern::Type(ty) => {
+                    Some(format!("_match.get_type() == Type::{}", runtime_type(*ty)))
+                }
+                Pattern::List { items, rest } => Some(format!(
+                    "_match.matches_list({}, {})",
+                    items.len(),
+                    rest.is_some()
+                )),
+            };
+            code.push(format!(
+                "{}{indent}if (!_matched{}) {{",
+                line_directive(&arm.span),
+                test.map_or(String::new(), |test| format!(" && {test}"))
+            ));
 
-        [
-            Keyword(Const),
-            Identifier(var),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] if filename != "main" && tabs == 0 => {
-            format!(
-                "const Value {filename}::{var}({});",
-                generate_expression(expression)
-            )
+            let mut body = Vec::new();
+            if let Pattern::List { items, rest } = &arm.pattern {
+                for (i, item) in items.iter().enumerate().filter(|(_, item)| *item != "_") {
+                    body.push(format!(
+                        "[[maybe_unused]] Value {item} = _match[Value({i})];"
+                    ));
+                }
+                if let Some(rest) = rest.as_ref().filter(|&rest| rest != "_") {
+                    body.push(format!(
+                        "[[maybe_unused]] Value {rest} = _match.slice({});",
+                        items.len()
+                    ));
+                }
+            }
+            let body_tabs = match &arm.guard {
+                Some(guard) => {
+                    body.push(format!("if ({}) {{", self.generate_condition(guard)));
+                    tabs + 3
+                }
+                None => tabs + 2,
+            };
+            let inner = "\t".repeat(body_tabs - 1);
+            code.extend(body.into_iter().map(|line| format!("{indent}\t{line}")));
+            code.push(format!("{inner}\t_matched = true;"));
+            code.push(self.generate_block(&arm.body, body_tabs));
+            if arm.guard.is_some() {
+                code.push(format!("{inner}}}"));
+            }
+            code.push(format!("{indent}}}"));
         }
 
-        [
-            Keyword(Const),
-            Identifier(var),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] => {
-            format!("const Value {var}({});", generate_expression(expression))
-        }
+        code.push(format!("{}}}", "\t".repeat(tabs)));
+        code.join("\n")
+    }
 
-        [Identifier(var), SpecialCharacter(Equals), expression @ ..] => {
-            format!("{var} = {};", generate_expression(expression))
+    /// Generates the target of an assignment, increment or decrement. Native
+    /// locals are left unboxed so the local itself changes, and assigning
+    /// to a missing map key adds it, where reading one is an error.
+    fn generate_target(&self, target: &Expression) -> String {
+        match &target.kind {
+            _ if self.is_native_local(target) => self.generate_native(target),
+            ExpressionKind::Index { object, index } => format!(
+                "{}.entry({})",
+                self.generate_target(object),
+                self.generate_expression(index)
+            ),
+            _ => self.generate_expression(target),
         }
+    }
 
-        [
-            Identifier(var),
-            SpecialCharacter(operation),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] if [Plus, Minus, Asterisk, Slash].contains(operation) => {
-            format!(
-                "{var} {}= {};",
-                to_operation_sign(operation),
-                generate_expression(expression)
-            )
+    /// Generates a condition, testing native values directly instead of
+    /// boxing them first.
+    fn generate_condition(&self, condition: &Expression) -> String {
+        match self.get_native_type(condition) {
+            Some(_) if self.uses_natives(condition) => self.generate_native(condition),
+            _ => self.generate_expression(condition),
         }
+    }
 
-        [
-            Identifier(file),
-            SpecialCharacter(Colon),
-            SpecialCharacter(Colon),
-            Identifier(var),
-            SpecialCharacter(Equals),
-            expression @ ..,
-        ] => {
-            format!("{file}::{var} = {};", generate_expression(expression))
+    fn generate_expression(&self, expression: &Expression) -> String {
+        if let Some(ty) = self.get_native_type(expression)
+            && self.uses_natives(expression)
+        {
+            return box_native(&self.generate_native(expression), ty);
         }
 
-        [Identifier(var), SpecialCharacter(OpenBracket)] => {
-            panic!("Missing index and assignment in array access for variable: {var}")
-        }
-        [Identifier(var), SpecialCharacter(OpenBracket), rest @ ..] => {
-            // Find the position of CloseBracket and Equals
-            let close_bracket_pos = rest
-                .iter()
-                .position(|t| t == &SpecialCharacter(CloseBracket));
-            let equals_pos = rest.iter().position(|t| t == &SpecialCharacter(Equals));
-            if let (Some(cb), Some(eq)) = (close_bracket_pos, equals_pos) {
-                if eq > cb {
-                    let index = &rest[..cb];
-                    let expression = &rest[eq + 1..];
-                    format!(
-                        "{var}[{}] = {};",
-                        generate_expression(index),
-                        generate_expression(expression)
-                    )
+        match &expression.kind {
+            ExpressionKind::Number(n) if n.contains('.') => format!("Value({n})"),
+            // Spelled as `int32_t` so that a folded `-2147483648`, which C++
+            // reads as a wider type, still picks the int constructor.
+            ExpressionKind::Number(n) => format!("Value(int32_t{{{n}}})"),
+            ExpressionKind::Str(s) => format!("Value(\"{}\")", escape(s)),
+            ExpressionKind::Character(c) => format!("Value('{}')", escape(c)),
+            ExpressionKind::Bool(b) => format!("Value({b})"),
+            ExpressionKind::Null => "Value(nullptr)".to_string(),
+            ExpressionKind::Identifier(id) => {
+                if BUILTINS.contains(&id.as_str()) {
+                    format!("_{id}")
                 } else {
-                    panic!(
-                        "Equals sign must come after closing bracket in array assignment for variable: {var}"
-                    )
+                    id.to_string()
                 }
-            } else {
-                panic!("Malformed array assignment for variable: {var}")
+            }
+            ExpressionKind::ModuleMember { module, name } => format!("{module}::{name}"),
+            ExpressionKind::List(items) => {
+                format!("Value(std::vector<Value>{{{}}})", self.generate_list(items))
+            }
+            ExpressionKind::Map(entries) => format!(
+                "Value(std::vector<std::pair<Value, Value>>{{{}}})",
+                entries
+                    .iter()
+                    .map(|(key, value)| format!(
+                        "{{{}, {}}}",
+                        self.generate_expression(key),
+                        self.generate_expression(value)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ExpressionKind::Range { start, end } => format!(
+                "Range({}, {})",
+                start.as_ref().map_or("Value(0)".to_string(), |start| {
+                    self.generate_expression(start)
+                }),
+                end.as_ref().map_or(
+                    "Value(2147483647)".to_string(), // Max 32-bit signed integer
+                    |end| self.generate_expression(end)
+                )
+            ),
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+            } => format!("(-{})", self.generate_expression(operand)),
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => format!("(!{})", self.generate_expression(operand)),
+            ExpressionKind::Binary {
+                operator: BinaryOperator::In,
+                left,
+                right,
+            } => format!(
+                "Value({}.contains({}))",
+                self.generate_expression(right),
+                self.generate_expression(left)
+            ),
+            ExpressionKind::Binary {
+                operator: operator @ (BinaryOperator::And | BinaryOperator::Or),
+                left,
+                right,
+            } => format!(
+                "Value(static_cast<bool>({}) {} static_cast<bool>({}))",
+                self.generate_expression(left),
+                operator.sign(),
+                self.generate_expression(right)
+            ),
+            ExpressionKind::Binary {
+                operator:
+                    operator @ (BinaryOperator::Equal
+                    | BinaryOperator::NotEqual
+                    | BinaryOperator::Less
+                    | BinaryOperator::Greater
+                    | BinaryOperator::LessEqual
+                    | BinaryOperator::GreaterEqual),
+                left,
+                right,
+            } => format!(
+                "Value({} {} {})",
+                self.generate_expression(left),
+                operator.sign(),
+                self.generate_expression(right)
+            ),
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => format!(
+                "({} {} {})",
+                self.generate_expression(left),
+                operator.sign(),
+                self.generate_expression(right)
+            ),
+            // A call through a member is a method call, rather than a call
+            // of the field.
+            ExpressionKind::Call {
+                callee, arguments, ..
+            } => match &callee.kind {
+                ExpressionKind::Member { object, name } => format!(
+                    "{}[\"{name}\"]({})",
+                    self.generate_expression(object),
+                    self.generate_arguments(expression, arguments, true)
+                ),
+                _ => format!(
+                    "{}({})",
+                    self.generate_expression(callee),
+                    self.generate_arguments(expression, arguments, false)
+                ),
+            },
+            ExpressionKind::Index { object, index } => {
+                format!(
+                    "{}[{}]",
+                    self.generate_expression(object),
+                    self.generate_expression(index)
+                )
+            }
+            ExpressionKind::Member { object, name } => {
+                format!("{}.field(\"{name}\")", self.generate_expression(object))
+            }
+            ExpressionKind::Lambda { params, body } => {
+                self.generate_lambda(params, body, &expression.span)
             }
         }
+    }
 
-        [Keyword(If), condition @ ..] => {
-            format!("if ({}) {{", generate_expression(condition))
-        }
-        [Keyword(Else)] => "else {".to_string(),
-        [Keyword(Else), Keyword(If), condition @ ..] => {
-            format!("else if ({}) {{", generate_expression(condition))
+    /// Generates a lambda as a C++ lambda wrapped in a `Value`. Locals are
+    /// captured by copy, which the resolver makes sure are never assigned
+    /// to. Missing arguments are `null`, and extra ones are ignored unless a
+    /// rest parameter collects them. The arguments arrive in `_args`, which
+    /// no Lyra name can shadow.
+    fn generate_lambda(&self, params: &[Parameter], body: &Block, span: &Span) -> String {
+        let tabs = self.tabs.get();
+        let indent = "\t".repeat(tabs + 1);
+        let mut code = params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                let value = if param.rest {
+                    generate_rest(i)
+                } else {
+                    format!("_args.size() > {i} ? _args[{i}] : Value(nullptr)")
+                };
+                format!("{indent}Value {} = {value};", param.name)
+            })
+            .collect::<Vec<_>>();
+        code.extend(self.generate_defaults(params, tabs + 1));
+        if yields(body) {
+            code.push(format!(
+                "{indent}return {};",
+                self.generate_generator(body, tabs + 1)
+            ));
+        } else {
+            let outer = self.in_generator.replace(false);
+            code.push(self.generate_block(body, tabs + 1));
+            self.in_generator.set(outer);
+            if !matches!(body.last(), Some(statement) if matches!(statement.kind, StatementKind::Return(_)))
+            {
+                code.push(format!("{indent}return Value(nullptr);"));
+            }
         }
 
-        [Keyword(Loop)] => "while (true) {".to_string(),
-
-        [Keyword(Loop), Identifier(var), Keyword(In), expression @ ..] => {
-            format!(
-                "for (const Value& {var} : {}) {{",
-                generate_iterator(expression)
-            )
-        }
+        format!(
+            // Module variables are at namespace scope, where a lambda can't
+            // capture anything, and has nothing to.
+            "Value(std::function<Value(const std::vector<Value>&)>([{}]([[maybe_unused]] const std::vector<Value>& _args) -> Value {{\n{}{}\n{}{}}}))",
+            if tabs == 0 { "" } else { "=" },
+            line_directive(span),
+            code.join("\n"),
+            line_directive(span),
+            "\t".repeat(tabs)
+        )
+    }
 
-        [Keyword(Loop), expression @ ..] => {
-            format!("while ({}) {{", generate_expression(expression))
+    /// Generates an expression with a native type as native C++. Only the
+    /// operands of logic and of the conversion built-ins can be dynamic, and
+    /// those are unboxed here.
+    fn generate_native(&self, expression: &Expression) -> String {
+        match &expression.kind {
+            ExpressionKind::Number(n) if n.contains('.') => n.to_string(),
+            ExpressionKind::Number(n) => format!("int32_t{{{n}}}"),
+            ExpressionKind::Bool(b) => b.to_string(),
+            ExpressionKind::Identifier(id) => id.to_string(),
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+            } => match self.get_native_type(expression) {
+                Some(Type::Int) => format!("_isub(0, {})", self.generate_number(operand)),
+                _ => format!("(-{})", self.generate_native(operand)),
+            },
+            ExpressionKind::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => format!("(!{})", self.generate_truth(operand)),
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => self.generate_native_binary(*operator, left, right),
+            ExpressionKind::Call {
+                callee, arguments, ..
+            } => {
+                let ExpressionKind::Identifier(name) = &callee.kind else {
+                    unreachable!("Only calls to built-in functions have a native type")
+                };
+                let argument = &arguments[0];
+                let native =
+                    self.get_native_type(argument).is_some() && self.uses_natives(argument);
+                match name.as_str() {
+                    "bool" => self.generate_truth(argument),
+                    "float" if native => {
+                        format!("static_cast<double>({})", self.generate_native(argument))
+                    }
+                    "int" if native && self.get_native_type(argument) == Some(Type::Float) => {
+                        format!("_ftoi({})", self.generate_native(argument))
+                    }
+                    "int" if native => {
+                        format!("static_cast<int32_t>({})", self.generate_native(argument))
+                    }
+                    "float" => {
+                        format!("_float({}).get_value()", self.generate_expression(argument))
+                    }
+                    _ => format!(
+                        "static_cast<int32_t>(_{name}({}).get_value())",
+                        self.generate_expression(argument)
+                    ),
+                }
+            }
+            _ => unreachable!("Only expressions with a native type are generated natively"),
         }
+    }
 
-        _ => {
-            panic!("Unexpected token sequence in file: {filename} - {tokens:?}")
+    fn generate_native_binary(
+        &self,
+        operator: BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+    ) -> String {
+        match operator {
+            BinaryOperator::And | BinaryOperator::Or => format!(
+                "({} {} {})",
+                self.generate_truth(left),
+                operator.sign(),
+                self.generate_truth(right)
+            ),
+            // Dividing by zero throws, as it does for `Value`s.
+            BinaryOperator::Divide | BinaryOperator::Modulo => format!(
+                "_{}{}({}, {})",
+                match crate::infer::get_binary_type(
+                    operator,
+                    self.get_native_type(left),
+                    self.get_native_type(right),
+                ) {
+                    Some(Type::Float) => "f",
+                    _ => "i",
+                },
+                if operator == BinaryOperator::Divide {
+                    "div"
+                } else {
+                    "mod"
+                },
+                self.generate_native(left),
+                self.generate_native(right)
+            ),
+            // Ints wrap around on overflow, as they do in the runtime.
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply
+                if crate::infer::get_binary_type(
+                    operator,
+                    self.get_native_type(left),
+                    self.get_native_type(right),
+                ) == Some(Type::Int) =>
+            {
+                format!(
+                    "_i{}({}, {})",
+                    match operator {
+                        BinaryOperator::Add => "add",
+                        BinaryOperator::Subtract => "sub",
+                        _ => "mul",
+                    },
+                    self.generate_number(left),
+                    self.generate_number(right)
+                )
+            }
+            _ => format!(
+                "({} {} {})",
+                self.generate_number(left),
+                operator.sign(),
+                self.generate_number(right)
+            ),
         }
-    };
-
-    add_tabs_after_newlines(&code, tabs, filename)
-}
+    }
 
-fn match_h_code(tokens: &Vec<Token>) -> String {
-    match tokens.as_slice() {
-        [
-            Identifier(function),
-            SpecialCharacter(OpenParenthesis),
-            params @ ..,
-            SpecialCharacter(CloseParenthesis),
-        ] => format!("Value {function}({});", generate_params(params)),
-
-        [Keyword(Let), Identifier(var), ..] => {
-            format!("extern Value {var};")
+    /// Generates an operand of arithmetic or a comparison. Bools count as
+    /// `0` and `1`, as they do in the runtime, and are widened so g++
+    /// doesn't take comparing them with other numbers as a mistake.
+    fn generate_number(&self, expression: &Expression) -> String {
+        match self.get_native_type(expression) {
+            Some(Type::Bool) => {
+                format!("static_cast<int32_t>({})", self.generate_native(expression))
+            }
+            _ => self.generate_native(expression),
         }
+    }
 
-        [Keyword(Const), Identifier(var), ..] => {
-            format!("extern const Value {var};")
+    /// Generates an operand of `not`, `and` or `or` as a native `bool`.
+    fn generate_truth(&self, expression: &Expression) -> String {
+        match self.get_native_type(expression) {
+            Some(Type::Bool) => self.generate_native(expression),
+            Some(_) => format!("static_cast<bool>({})", self.generate_native(expression)),
+            None => format!(
+                "static_cast<bool>({})",
+                self.generate_expression(expression)
+            ),
         }
-
-        _ => "".to_string(),
     }
-}
 
-fn add_tabs_after_newlines(code: &str, tabs: u8, filename: &str) -> String {
-    let tab_str = "\t".repeat(tabs as usize);
-    code.lines()
-        .map(|line| {
-            if filename == "main" && !line.contains("#include") {
-                format!("\t{tab_str}{line}")
-            } else {
-                format!("{tab_str}{line}")
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    /// Generates a call's arguments in the order of the parameters they go
+    /// to, when the resolver matched them up. A method collects its own rest
+    /// arguments when it is dispatched, so they are passed one by one.
+    fn generate_arguments(
+        &self,
+        call: &Expression,
+        arguments: &[Expression],
+        method: bool,
+    ) -> String {
+        let Some(slots) = self.calls.get(&call_key(&call.span)) else {
+            return self.generate_list(arguments);
+        };
 
-fn to_operation_sign(operation: &SpecialCharacter) -> &str {
-    match operation {
-        Plus => "+",
-        Minus => "-",
-        Asterisk => "*",
-        Slash => "/",
-        _ => panic!("Unexpected token for operation sign: {:?}", operation),
+        slots
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Argument(i) => Some(self.generate_expression(&arguments[*i])),
+                Slot::Default => Some("Value(nullptr)".to_string()),
+                Slot::Rest(indices) if method && indices.is_empty() => None,
+                Slot::Rest(indices) => {
+                    let items = indices
+                        .iter()
+                        .map(|&i| self.generate_expression(&arguments[i]))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    Some(if method {
+                        items
+                    } else {
+                        format!("Value(std::vector<Value>{{{items}}})")
+                    })
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     }
-}
 
-fn generate_args(num_args: usize) -> String {
-    let mut args_str = String::new();
-
-    for i in 0..num_args {
-        args_str.push_str(&format!("args[{i}], "));
+    fn generate_list(&self, items: &[Expression]) -> String {
+        items
+            .iter()
+            .map(|item| self.generate_expression(item))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    args_str.pop();
-    args_str.pop();
-
-    args_str
-}
+    fn get_native_type(&self, expression: &Expression) -> Option<Type> {
+        crate::infer::get_native_type(expression, &|span| {
+            self.natives.uses.get(&position(span)).copied()
+        })
+    }
 
-fn generate_params(params: &[Token]) -> String {
-    let mut param_str = String::new();
-    let mut is_comma = false;
+    fn is_native_local(&self, expression: &Expression) -> bool {
+        matches!(expression.kind, ExpressionKind::Identifier(_))
+            && self.natives.uses.contains_key(&position(&expression.span))
+    }
 
-    for param in params {
-        match param {
-            SpecialCharacter(Comma) if is_comma => {
-                param_str.push_str(", ");
-                is_comma = false;
+    /// Whether an expression reads a native local. Expressions that don't
+    /// are generated just as in debug builds.
+    fn uses_natives(&self, expression: &Expression) -> bool {
+        match &expression.kind {
+            ExpressionKind::Identifier(_) => self.is_native_local(expression),
+            ExpressionKind::Unary { operand, .. } => self.uses_natives(operand),
+            ExpressionKind::Binary { left, right, .. } => {
+                self.uses_natives(left) || self.uses_natives(right)
             }
-            Identifier(name) if !is_comma => {
-                param_str.push_str(&format!("Value {name}"));
-                is_comma = true;
+            ExpressionKind::Call { arguments, .. } => {
+                arguments.iter().any(|argument| self.uses_natives(argument))
             }
-            _ => panic!("Unexpected token in function parameters: {:?}", param),
+            _ => false,
         }
     }
+}
 
-    param_str
+/// Points g++ at the Lyra line a piece of generated code came from, so its
+/// errors can be reported against the source the user wrote.
+fn line_directive(span: &Span) -> String {
+    format!("#line {} \"{}\"\n", span.line, escape(&span.file))
 }
 
-fn generate_iterator(expression: &[Token]) -> String {
-    if expression.is_empty() {
-        panic!("Iterator requires at least one token");
+/// The runtime's name for a type, in its `Type` enum.
+fn runtime_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "Int",
+        Type::Float => "Float",
+        Type::Bool => "Bool",
+        Type::Char => "Char",
+        Type::String => "String",
+        Type::List => "List",
+        Type::Map => "Map",
     }
+}
 
-    if let Some(dotdot_pos) = expression
-        .windows(2)
-        .position(|window| matches!(window, [SpecialCharacter(Dot), SpecialCharacter(Dot)]))
-    {
-        let start_tokens = &expression[..dotdot_pos];
-        let end_tokens = &expression[dotdot_pos + 2..];
-
-        let start_expr = if start_tokens.is_empty() {
-            "Value(0)".to_string()
-        } else {
-            generate_expression(start_tokens)
-        };
-
-        let end_expr = if end_tokens.is_empty() {
-            "Value(2147483647)".to_string() // Max 32-bit signed integer
-        } else {
-            generate_expression(end_tokens)
-        };
+fn native_name(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "int32_t",
+        Type::Float => "double",
+        Type::Bool => "bool",
+        _ => unreachable!("Only ints, floats and bools are unboxed"),
+    }
+}
 
-        format!("Range({}, {})", start_expr, end_expr)
-    } else {
-        generate_expression(expression)
+/// Wraps native code in a `Value`.
+fn box_native(code: &str, ty: Type) -> String {
+    match ty {
+        Type::Int => format!("Value(static_cast<int32_t>({code}))"),
+        Type::Float => format!("Value(static_cast<double>({code}))"),
+        _ => format!("Value(static_cast<bool>({code}))"),
     }
 }
 
-fn generate_expression(expression: &[Token]) -> String {
-    if expression.is_empty() {
-        panic!("Expression requires at least one token");
+/// Generates the body of a struct's constructor, which makes an object
+/// named after the struct and sets each field from its parameter.
+fn generate_constructor(name: &str, fields: &[Parameter], tabs: usize) -> String {
+    let indent = "\t".repeat(tabs);
+    let mut code = vec![format!("{indent}Value _self = Value::object(\"{name}\");")];
+    code.extend(
+        fields
+            .iter()
+            .map(|field| format!("{indent}_self.set_field(\"{0}\", {0});", field.name)),
+    );
+    code.push(format!("{indent}return _self;"));
+    code.join("\n")
+}
+
+/// The C++ function a method is generated as. Those of a struct are kept
+/// apart from those of any value with the same name.
+pub fn method_function(method: &str, receiver: Option<&str>) -> String {
+    match receiver {
+        Some(receiver) => format!("_{receiver}_method_{method}"),
+        None => format!("_method_{method}"),
     }
+}
 
-    let mut expression_str = String::new();
-    let mut tokens = expression.iter().peekable();
-
-    while let Some(token) = tokens.next() {
-        match token {
-            Identifier(id) => {
-                if [
-                    "print", "type", "len", "exit", "null", "int", "float", "bool", "char",
-                    "string", "list",
-                ]
-                .contains(&id.as_str())
-                {
-                    expression_str.push_str(&format!("_{}", id));
-                } else {
-                    expression_str.push_str(id);
-                }
-            }
-            Literal(value) => match value {
-                Str(s) => {
-                    expression_str.push_str(&format!("Value(\"{s}\")"));
-                }
-                Character(c) => {
-                    expression_str.push_str(&format!("Value('{c}')"));
-                }
-                Number(n) => {
-                    expression_str.push_str(&format!("Value({n})"));
-                }
-            },
-            SpecialCharacter(Dot) => {
-                if let Some(Identifier(method)) = tokens.next() {
-                    expression_str.push_str(&format!("[\"{method}\"]"));
-                } else {
-                    panic!(
-                        "Expected method name after dot, {:?}, {:?}",
-                        token, expression
-                    );
-                }
-            }
-            SpecialCharacter(Comma) => {
-                expression_str.push_str(", ");
+/// Generates the arguments a registered method passes on from the ones it
+/// was dispatched with, where missing ones are only allowed for parameters
+/// with defaults.
+fn generate_args(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| {
+            if param.rest {
+                generate_rest(i)
+            } else if param.default.is_some() {
+                format!("_args.size() > {i} ? _args[{i}] : Value(nullptr)")
+            } else {
+                format!("_args[{i}]")
             }
-            SpecialCharacter(Equals) => expression_str.push_str("="),
-            SpecialCharacter(ExclamationMark) => expression_str.push_str("!"),
-            SpecialCharacter(Colon) => expression_str.push_str(":"),
-            SpecialCharacter(Plus) => expression_str.push_str("+"),
-            SpecialCharacter(Minus) => expression_str.push_str("-"),
-            SpecialCharacter(Asterisk) => expression_str.push_str("*"),
-            SpecialCharacter(Slash) => expression_str.push_str("/"),
-            SpecialCharacter(Percent) => expression_str.push_str("%"),
-            SpecialCharacter(LargerThan) => expression_str.push_str(">"),
-            SpecialCharacter(SmallerThan) => expression_str.push_str("<"),
-            SpecialCharacter(OpenParenthesis) => expression_str.push_str("("),
-            SpecialCharacter(CloseParenthesis) => expression_str.push_str(")"),
-            SpecialCharacter(OpenBracket) => expression_str.push_str("["),
-            SpecialCharacter(CloseBracket) => expression_str.push_str("]"),
-            SpecialCharacter(OpenBrace) => expression_str.push_str("Value(std::vector<Value>{"),
-            SpecialCharacter(CloseBrace) => expression_str.push_str("})"),
-            Keyword(True) => expression_str.push_str("Value(true)"),
-            Keyword(False) => expression_str.push_str("Value(false)"),
-            Keyword(Null) => expression_str.push_str("Value(nullptr)"),
-            Keyword(And) => expression_str.push_str("&&"),
-            Keyword(Or) => expression_str.push_str("||"),
-            Keyword(Not) => expression_str.push_str("!"),
-            _ => panic!("Unexpected token in expression: {:?}", token),
-        }
-    }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Generates a list of the arguments from `start` on, for a rest parameter.
+fn generate_rest(start: usize) -> String {
+    format!(
+        "_args.size() > {start} ? Value(std::vector<Value>(_args.begin() + {start}, _args.end())) : Value(std::vector<Value>{{}})"
+    )
+}
 
-    expression_str
+fn generate_params(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .map(|param| format!("Value {}", param.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn escape(literal: &str) -> String {
+    literal
+        .chars()
+        .map(|c| match c {
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '\r' => "\\r".to_string(),
+            '\0' => "\\0".to_string(),
+            '\\' => "\\\\".to_string(),
+            '\'' => "\\'".to_string(),
+            '"' => "\\\"".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
 }
@@ -0,0 +1,96 @@
+use crate::ast::{Import, Module};
+use crate::types::Diagnostic;
+use std::collections::HashMap;
+
+/// Checks the module dependency graph: every import must name a file in
+/// `src`, and modules can't import each other in a cycle, since each one
+/// would need the other to be set up first.
+pub fn check_imports(modules: &[Module]) -> Vec<Diagnostic> {
+    let graph = modules
+        .iter()
+        .map(|module| (module.name.as_str(), &module.imports))
+        .collect::<HashMap<_, _>>();
+    let mut diagnostics = Vec::new();
+
+    for module in modules {
+        for import in &module.imports {
+            if !graph.contains_key(import.module.as_str()) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "cannot find module `{}`: there is no src/{}.ly",
+                        import.module, import.module
+                    ),
+                    &import.span,
+                ));
+            }
+        }
+    }
+
+    // Walking the modules in name order keeps the reported cycles the same
+    // from one build to the next.
+    let mut names = graph.keys().copied().collect::<Vec<_>>();
+    names.sort();
+
+    let mut states = HashMap::new();
+    for name in names {
+        find_cycles(name, &graph, &mut states, &mut Vec::new(), &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Visiting,
+    Done,
+}
+
+/// Depth-first search from `module`. `path` holds the modules currently
+/// being visited, so an import of one of them closes a cycle.
+fn find_cycles<'a>(
+    module: &'a str,
+    graph: &HashMap<&'a str, &'a Vec<Import>>,
+    states: &mut HashMap<&'a str, State>,
+    path: &mut Vec<&'a str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if states.contains_key(module) {
+        return;
+    }
+
+    states.insert(module, State::Visiting);
+    path.push(module);
+
+    for import in graph[module].iter() {
+        let imported = import.module.as_str();
+
+        match states.get(imported) {
+            Some(State::Visiting) => {
+                let start = path
+                    .iter()
+                    .position(|&name| name == imported)
+                    .expect("A module being visited is on the path");
+                let cycle = path[start..]
+                    .iter()
+                    .chain(std::iter::once(&imported))
+                    .copied()
+                    .collect::<Vec<_>>();
+
+                let message = if cycle.len() == 2 {
+                    format!("module `{module}` imports itself")
+                } else {
+                    format!("import cycle: {}", cycle.join(" -> "))
+                };
+                diagnostics.push(Diagnostic::error(message, &import.span));
+            }
+            Some(State::Done) => {}
+            None if graph.contains_key(imported) => {
+                find_cycles(imported, graph, states, path, diagnostics)
+            }
+            None => {}
+        }
+    }
+
+    path.pop();
+    states.insert(module, State::Done);
+}
@@ -0,0 +1,491 @@
+use crate::ast::*;
+use crate::types::Span;
+use std::collections::HashMap;
+
+/// A line and column in a file, identifying a declaration or a use of a
+/// name.
+pub type Position = (usize, usize);
+
+pub fn position(span: &Span) -> Position {
+    (span.line, span.column)
+}
+
+/// Locals proven to always hold an `int`, `float` or `bool`, which release
+/// builds store as native C++ values instead of `Value`s.
+#[derive(Default)]
+pub struct Natives {
+    /// The type of each unboxed local, keyed by the statement declaring it.
+    pub declarations: HashMap<Position, Type>,
+    /// The type of each identifier that refers to an unboxed local.
+    pub uses: HashMap<Position, Type>,
+}
+
+/// Finds the locals of a module that can be unboxed. Every local starts
+/// out with the type of its initial value, then any local that is given a
+/// value of another type, could be `null` or has its elements assigned is
+/// dropped, until the remaining ones agree. Parameters and module
+/// variables stay `Value`s, since other code sees them.
+pub fn infer(module: &Module) -> Natives {
+    let mut collector = Collector {
+        scopes: vec![HashMap::new()],
+        locals: HashMap::new(),
+        uses: HashMap::new(),
+    };
+    collector.collect_module(module);
+    let Collector { locals, uses, .. } = collector;
+
+    let mut declarations = locals.keys().copied().collect::<Vec<_>>();
+    // A local's initial value can only use locals declared before it.
+    declarations.sort();
+
+    let mut types = HashMap::new();
+    for declaration in declarations {
+        let lookup = |span: &Span| get_use_type(span, &uses, &types);
+        if let Some(ty) = get_source_type(&locals[&declaration].initial, &lookup) {
+            types.insert(declaration, ty);
+        }
+    }
+
+    loop {
+        let rejected = types
+            .iter()
+            .filter(|&(declaration, &ty)| {
+                let local = &locals[declaration];
+                let lookup = |span: &Span| get_use_type(span, &uses, &types);
+                get_source_type(&local.initial, &lookup) != Some(ty)
+                    || !local
+                        .assignments
+                        .iter()
+                        .all(|assignment| assignment_fits(assignment, ty, &lookup))
+            })
+            .map(|(&declaration, _)| declaration)
+            .collect::<Vec<_>>();
+
+        if rejected.is_empty() {
+            break;
+        }
+        for declaration in rejected {
+            types.remove(&declaration);
+        }
+    }
+
+    Natives {
+        uses: uses
+            .iter()
+            .filter_map(|(&use_position, declaration)| {
+                Some((use_position, *types.get(declaration)?))
+            })
+            .collect(),
+        declarations: types,
+    }
+}
+
+fn get_use_type(
+    span: &Span,
+    uses: &HashMap<Position, Position>,
+    types: &HashMap<Position, Type>,
+) -> Option<Type> {
+    types.get(uses.get(&position(span))?).copied()
+}
+
+/// Where a local's value comes from.
+enum Source<'a> {
+    Value(&'a Expression),
+    Compound(BinaryOperator, &'a Expression),
+    Update,
+    Range(Option<&'a Expression>, Option<&'a Expression>),
+    /// An assignment that rules out unboxing, such as one to an element.
+    Unknown,
+}
+
+fn get_source_type(source: &Source, lookup: &dyn Fn(&Span) -> Option<Type>) -> Option<Type> {
+    match source {
+        Source::Value(expression) => get_native_type(expression, lookup),
+        Source::Range(start, end) => [start, end]
+            .into_iter()
+            .flatten()
+            .all(|bound| get_native_type(bound, lookup) == Some(Type::Int))
+            .then_some(Type::Int),
+        Source::Compound(..) | Source::Update | Source::Unknown => None,
+    }
+}
+
+fn assignment_fits(assignment: &Source, ty: Type, lookup: &dyn Fn(&Span) -> Option<Type>) -> bool {
+    match assignment {
+        Source::Compound(operator, value) => {
+            get_binary_type(*operator, Some(ty), get_native_type(value, lookup)) == Some(ty)
+        }
+        Source::Update => matches!(ty, Type::Int | Type::Float),
+        _ => get_source_type(assignment, lookup) == Some(ty),
+    }
+}
+
+/// The native type an expression evaluates to, if it has one, given the
+/// type of each unboxed local it uses. Numbers mix into an `int` or a
+/// `float` as they do in the runtime, and logic always gives a `bool`.
+pub fn get_native_type(
+    expression: &Expression,
+    lookup: &dyn Fn(&Span) -> Option<Type>,
+) -> Option<Type> {
+    match &expression.kind {
+        ExpressionKind::Number(n) if n.contains('.') => Some(Type::Float),
+        ExpressionKind::Number(_) => Some(Type::Int),
+        ExpressionKind::Bool(_) => Some(Type::Bool),
+        ExpressionKind::Identifier(_) => lookup(&expression.span),
+        ExpressionKind::Unary {
+            operator: UnaryOperator::Negate,
+            operand,
+        } => match get_native_type(operand, lookup)? {
+            Type::Float => Some(Type::Float),
+            _ => Some(Type::Int),
+        },
+        ExpressionKind::Unary {
+            operator: UnaryOperator::Not,
+            ..
+        } => Some(Type::Bool),
+        ExpressionKind::Binary {
+            operator,
+            left,
+            right,
+        } => get_binary_type(
+            *operator,
+            get_native_type(left, lookup),
+            get_native_type(right, lookup),
+        ),
+        ExpressionKind::Call {
+            callee, arguments, ..
+        } if arguments.len() == 1 => match &callee.kind {
+            ExpressionKind::Identifier(name) => match name.as_str() {
+                "len" | "int" => Some(Type::Int),
+                "float" => Some(Type::Float),
+                "bool" => Some(Type::Bool),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn get_binary_type(
+    operator: BinaryOperator,
+    left: Option<Type>,
+    right: Option<Type>,
+) -> Option<Type> {
+    match operator {
+        BinaryOperator::And | BinaryOperator::Or => Some(Type::Bool),
+        // Membership is always tested on boxed values.
+        BinaryOperator::In => None,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::Less
+        | BinaryOperator::Greater
+        | BinaryOperator::LessEqual
+        | BinaryOperator::GreaterEqual => {
+            left?;
+            right?;
+            Some(Type::Bool)
+        }
+        _ => match (left?, right?) {
+            (Type::Float, _) | (_, Type::Float) => Some(Type::Float),
+            _ => Some(Type::Int),
+        },
+    }
+}
+
+/// A local that might be unboxed, with everything assigned to it.
+struct Local<'a> {
+    initial: Source<'a>,
+    assignments: Vec<Source<'a>>,
+}
+
+/// Walks a module, matching every use of a name to the local it refers to.
+struct Collector<'a> {
+    /// Each name in scope, with the position of its declaration if it is a
+    /// local that might be unboxed.
+    scopes: Vec<HashMap<&'a str, Option<Position>>>,
+    locals: HashMap<Position, Local<'a>>,
+    uses: HashMap<Position, Position>,
+}
+
+impl<'a> Collector<'a> {
+    fn collect_module(&mut self, module: &'a Module) {
+        for statement in &module.statements {
+            match &statement.kind {
+                StatementKind::Function { params, body, .. }
+                | StatementKind::Method { params, body, .. } => {
+                    // Functions are generated outside of `main()`, so they
+                    // don't see the locals of `main.ly`.
+                    let locals = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+                    self.collect_function(params, body);
+                    self.scopes = locals;
+                }
+                StatementKind::Let { value, .. } if module.name != "main" => {
+                    self.collect_optional(value.as_ref())
+                }
+                StatementKind::Const { value, .. } if module.name != "main" => {
+                    self.collect_expression(value)
+                }
+                _ => self.collect_statement(statement),
+            }
+        }
+    }
+
+    fn collect_function(&mut self, params: &'a [Parameter], body: &'a Block) {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.collect_optional(param.default.as_ref());
+            self.scopes
+                .last_mut()
+                .expect("A scope is always open")
+                .insert(&param.name, None);
+        }
+        self.collect_statements(body);
+        self.scopes.pop();
+    }
+
+    fn collect_block(&mut self, block: &'a Block) {
+        self.scopes.push(HashMap::new());
+        self.collect_statements(block);
+        self.scopes.pop();
+    }
+
+    fn collect_statements(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            self.collect_statement(statement);
+        }
+    }
+
+    fn collect_statement(&mut self, statement: &'a Statement) {
+        match &statement.kind {
+            StatementKind::Let {
+                name,
+                value: Some(value),
+                ..
+            }
+            | StatementKind::Const { name, value, .. } => {
+                self.collect_expression(value);
+                self.declare(name, statement, Source::Value(value));
+            }
+            StatementKind::Let {
+                name, value: None, ..
+            } => {
+                self.scopes
+                    .last_mut()
+                    .expect("A scope is always open")
+                    .insert(name, None);
+            }
+            StatementKind::Assign {
+                target,
+                operator,
+                value,
+            } => {
+                self.collect_expression(target);
+                self.collect_expression(value);
+                self.assign(
+                    target,
+                    match operator {
+                        Some(operator) => Source::Compound(*operator, value),
+                        None => Source::Value(value),
+                    },
+                );
+            }
+            StatementKind::Update { target, .. } => {
+                self.collect_expression(target);
+                self.assign(target, Source::Update);
+            }
+            StatementKind::Expression(expression) => self.collect_expression(expression),
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for (condition, body) in branches {
+                    self.collect_expression(condition);
+                    self.collect_block(body);
+                }
+                if let Some(body) = else_body {
+                    self.collect_block(body);
+                }
+            }
+            StatementKind::Loop { body } => self.collect_block(body),
+            StatementKind::While { condition, body } => {
+                self.collect_expression(condition);
+                self.collect_block(body);
+            }
+            StatementKind::For {
+                variable,
+                value_variable,
+                iterable,
+                body,
+            } => {
+                self.collect_expression(iterable);
+                let source = match &iterable.kind {
+                    ExpressionKind::Range { start, end } => {
+                        Source::Range(start.as_deref(), end.as_deref())
+                    }
+                    _ => Source::Unknown,
+                };
+
+                self.scopes.push(HashMap::new());
+                self.declare(variable, statement, source);
+                if let Some(value_variable) = value_variable {
+                    self.scopes
+                        .last_mut()
+                        .expect("A scope is always open")
+                        .insert(value_variable, None);
+                }
+                self.collect_statements(body);
+                self.scopes.pop();
+            }
+            StatementKind::Match { value, arms } => {
+                self.collect_expression(value);
+                for arm in arms {
+                    self.scopes.push(
+                        arm.pattern
+                            .bindings()
+                            .into_iter()
+                            .map(|name| (name, None))
+                            .collect(),
+                    );
+                    self.collect_optional(arm.guard.as_ref());
+                    self.collect_statements(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            StatementKind::Try {
+                body,
+                variable,
+                catch_body,
+                finally_body,
+            } => {
+                self.collect_block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes
+                        .push(variable.iter().map(|name| (name.as_str(), None)).collect());
+                    self.collect_statements(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.collect_block(finally_body);
+                }
+            }
+            StatementKind::Throw(value) | StatementKind::Yield(value) => {
+                self.collect_expression(value)
+            }
+            StatementKind::Break | StatementKind::Continue | StatementKind::Struct { .. } => {}
+            StatementKind::Return(value) => self.collect_optional(value.as_ref()),
+            StatementKind::Function { .. } | StatementKind::Method { .. } => {
+                unreachable!("Definitions only appear at the top level of a file")
+            }
+        }
+    }
+
+    fn collect_optional(&mut self, expression: Option<&'a Expression>) {
+        if let Some(expression) = expression {
+            self.collect_expression(expression);
+        }
+    }
+
+    fn collect_expression(&mut self, expression: &'a Expression) {
+        match &expression.kind {
+            ExpressionKind::Identifier(name) => {
+                if let Some(declaration) = self.lookup(name) {
+                    self.uses.insert(position(&expression.span), declaration);
+                }
+            }
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.collect_expression(item);
+                }
+            }
+            ExpressionKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.collect_expression(key);
+                    self.collect_expression(value);
+                }
+            }
+            ExpressionKind::Range { start, end } => {
+                self.collect_optional(start.as_deref());
+                self.collect_optional(end.as_deref());
+            }
+            ExpressionKind::Unary { operand, .. } => self.collect_expression(operand),
+            ExpressionKind::Binary { left, right, .. } => {
+                self.collect_expression(left);
+                self.collect_expression(right);
+            }
+            ExpressionKind::Call {
+                callee, arguments, ..
+            } => {
+                self.collect_expression(callee);
+                for argument in arguments {
+                    self.collect_expression(argument);
+                }
+            }
+            ExpressionKind::Index { object, index } => {
+                self.collect_expression(object);
+                self.collect_expression(index);
+            }
+            ExpressionKind::Member { object, .. } => self.collect_expression(object),
+            // Captured locals are copied into the lambda with their native
+            // types, so uses inside it are matched like any other.
+            ExpressionKind::Lambda { params, body } => self.collect_function(params, body),
+            ExpressionKind::Number(_)
+            | ExpressionKind::Str(_)
+            | ExpressionKind::Character(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Null
+            | ExpressionKind::ModuleMember { .. } => {}
+        }
+    }
+
+    fn declare(&mut self, name: &'a str, statement: &Statement, initial: Source<'a>) {
+        let declaration = position(&statement.span);
+        self.scopes
+            .last_mut()
+            .expect("A scope is always open")
+            .insert(name, Some(declaration));
+        self.locals.insert(
+            declaration,
+            Local {
+                initial,
+                assignments: Vec::new(),
+            },
+        );
+    }
+
+    /// Records an assignment to a local. Assigning to an element or a field
+    /// keeps the local boxed, since only lists, strings and structs have
+    /// them.
+    fn assign(&mut self, target: &Expression, source: Source<'a>) {
+        let (name, source) = match &target.kind {
+            ExpressionKind::Identifier(name) => (name, source),
+            ExpressionKind::Index { object, .. } | ExpressionKind::Member { object, .. } => {
+                let mut root = object;
+                while let ExpressionKind::Index { object, .. }
+                | ExpressionKind::Member { object, .. } = &root.kind
+                {
+                    root = object;
+                }
+                match &root.kind {
+                    ExpressionKind::Identifier(name) => (name, Source::Unknown),
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+
+        if let Some(declaration) = self.lookup(name)
+            && let Some(local) = self.locals.get_mut(&declaration)
+        {
+            local.assignments.push(source);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Position> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .flatten()
+    }
+}
@@ -14,10 +14,10 @@ pub fn get_filenames() -> Vec<String> {
     for entry in std::fs::read_dir("src").expect("Failed to read src directory") {
         let entry = entry.expect("Failed to read directory entry");
         let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("ly") {
-            if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-                filenames.push(filename.to_string());
-            }
+        if path.extension().and_then(|s| s.to_str()) == Some("ly")
+            && let Some(filename) = path.file_name().and_then(|s| s.to_str())
+        {
+            filenames.push(filename.to_string());
         }
     }
 
@@ -1,30 +1,255 @@
-use core::panic;
-
 use crate::types::Keyword::*;
+use crate::types::LexErrorKind::*;
 use crate::types::Literal::*;
 use crate::types::SpecialCharacter::*;
-use crate::types::Token::*;
-
-pub fn get_tokens(line: &str) -> (Vec<crate::types::Token>, u8) {
-    let mut tokens = Vec::new();
-    let mut chars = line.chars().peekable();
-    let mut spaces = 0;
-
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '\t' => {
-                spaces += 4;
-                chars.next();
+use crate::types::TokenKind::*;
+use crate::types::{LexError, LexErrorKind, Line, Span, Token};
+
+/// Lexes a whole file into logical lines. A line continues onto the next
+/// one while a `(`, `[` or `{` is still open, or when it ends with `\`.
+/// Lines without tokens are dropped.
+///
+/// A lambda whose `=>` ends a line inside brackets can have its body on the
+/// lines indented past the statement. Those are lexed as lines of their own,
+/// which come after the statement's line, and the statement carries on at
+/// the first line that isn't indented past it.
+///
+/// Lexing carries on past errors: a line that fails to lex keeps the tokens
+/// read before the error and is marked with `has_errors`, and every error is
+/// returned alongside the lines. A bracket counts as unclosed once a line
+/// that can only start a new statement appears at or left of the
+/// indentation of the line that opened it.
+pub fn get_lines(source: &[String], file: &str) -> (Vec<Line>, Vec<LexError>) {
+    let mut reader = LineReader::default();
+    let mut ends_with_arrow = false;
+
+    for (index, text) in source.iter().enumerate() {
+        let mut tokens = Vec::new();
+        let result = get_tokens(text, file, index + 1, &mut tokens);
+        let has_errors = result.is_err();
+        reader.errors.extend(result.err());
+
+        let continued =
+            tokens.last().map(|token| &token.kind) == Some(&SpecialCharacter(Backslash));
+        if continued {
+            tokens.pop();
+        }
+        let indent = get_indent(text);
+        let blank = tokens.is_empty() && !has_errors;
+
+        if !blank {
+            while reader
+                .suspended
+                .last()
+                .is_some_and(|outer| indent <= outer.line.indent)
+            {
+                reader.resume();
+                ends_with_arrow = false;
             }
-            ' ' => {
-                spaces += 1;
-                chars.next();
+
+            if ends_with_arrow
+                && let Some(line) = reader.current.take_if(|line| indent > line.indent)
+            {
+                reader.suspend(line);
             }
-            _ => break,
         }
+
+        if let Some(line) = &reader.current
+            && !reader.open_brackets.is_empty()
+            && indent <= line.indent
+            && starts_statement(&tokens)
+        {
+            reader.end_line();
+        }
+
+        // The rest of a line that failed to lex is unknown, so its brackets
+        // are left out rather than guessed at.
+        for token in tokens.iter().filter(|_| !has_errors) {
+            match token.kind {
+                SpecialCharacter(OpenParenthesis)
+                | SpecialCharacter(OpenBracket)
+                | SpecialCharacter(OpenBrace) => reader.open_brackets.push(token.clone()),
+                SpecialCharacter(CloseParenthesis)
+                | SpecialCharacter(CloseBracket)
+                | SpecialCharacter(CloseBrace) => {
+                    reader.open_brackets.pop();
+                }
+                _ => {}
+            }
+        }
+
+        if !blank {
+            ends_with_arrow = !continued
+                && !has_errors
+                && !reader.open_brackets.is_empty()
+                && tokens.last().map(|token| &token.kind) == Some(&SpecialCharacter(FatArrow));
+        }
+
+        match &mut reader.current {
+            Some(line) => {
+                line.tokens.extend(tokens);
+                line.has_errors |= has_errors;
+            }
+            None if blank => continue,
+            None => {
+                reader.current = Some(Line {
+                    tokens,
+                    indent,
+                    has_errors,
+                })
+            }
+        }
+
+        if !continued && reader.open_brackets.is_empty() {
+            reader.end_line();
+        }
+    }
+
+    while !reader.suspended.is_empty() {
+        reader.resume();
+    }
+    reader.end_line();
+    (reader.lines, reader.errors)
+}
+
+/// The logical lines read so far, and the state of the one being read.
+#[derive(Default)]
+struct LineReader {
+    lines: Vec<Line>,
+    errors: Vec<LexError>,
+    current: Option<Line>,
+    open_brackets: Vec<Token>,
+    /// The lines of the lambda bodies in `current`, which follow it.
+    bodies: Vec<Line>,
+    /// The lines left open while the bodies of lambdas in them are read,
+    /// innermost last.
+    suspended: Vec<Suspended>,
+}
+
+/// A line left open while the body of a lambda in it is read, with what
+/// was read before it.
+struct Suspended {
+    line: Line,
+    open_brackets: Vec<Token>,
+    bodies: Vec<Line>,
+    lines: Vec<Line>,
+}
+
+impl LineReader {
+    /// Ends the line being read, followed by the lines of its lambda bodies.
+    /// Brackets it leaves open are reported as unclosed.
+    fn end_line(&mut self) {
+        if let Some(bracket) = self.open_brackets.first() {
+            self.errors.push(unclosed_bracket(bracket));
+            self.open_brackets.clear();
+
+            if let Some(line) = &mut self.current {
+                line.has_errors = true;
+            }
+        }
+
+        self.lines.extend(self.current.take());
+        self.lines.append(&mut self.bodies);
     }
 
-    while let Some(&ch) = chars.peek() {
+    /// Leaves `line` open, to read the body of its last lambda.
+    fn suspend(&mut self, line: Line) {
+        self.suspended.push(Suspended {
+            line,
+            open_brackets: std::mem::take(&mut self.open_brackets),
+            bodies: std::mem::take(&mut self.bodies),
+            lines: std::mem::take(&mut self.lines),
+        });
+    }
+
+    /// Ends the innermost lambda body being read and carries on with the
+    /// line it's in.
+    fn resume(&mut self) {
+        let Some(outer) = self.suspended.pop() else {
+            return;
+        };
+        self.end_line();
+        let body = std::mem::replace(&mut self.lines, outer.lines);
+        self.current = Some(outer.line);
+        self.open_brackets = outer.open_brackets;
+        self.bodies = outer.bodies;
+        self.bodies.extend(body);
+    }
+}
+
+fn unclosed_bracket(bracket: &Token) -> LexError {
+    let character = match bracket.kind {
+        SpecialCharacter(OpenBracket) => '[',
+        SpecialCharacter(OpenBrace) => '{',
+        _ => '(',
+    };
+
+    LexError {
+        kind: UnclosedBracket(character),
+        span: bracket.span.clone(),
+    }
+}
+
+fn starts_statement(tokens: &[Token]) -> bool {
+    matches!(
+        tokens.first().map(|token| &token.kind),
+        Some(Keyword(
+            Let | Const
+                | Method
+                | Struct
+                | Return
+                | Import
+                | If
+                | Else
+                | Loop
+                | Match
+                | Try
+                | Catch
+                | Finally
+                | Throw
+                | Yield
+                | Break
+                | Continue
+        ))
+    )
+}
+
+/// Indentation level of a line: four spaces or one tab per level.
+fn get_indent(line: &str) -> u8 {
+    let spaces: usize = line
+        .chars()
+        .map_while(|ch| match ch {
+            '\t' => Some(4),
+            ' ' => Some(1),
+            _ => None,
+        })
+        .sum();
+
+    (spaces / 4) as u8
+}
+
+/// Appends the tokens of `line` to `tokens`, stopping at the first error.
+fn get_tokens(
+    line: &str,
+    file: &str,
+    line_number: usize,
+    tokens: &mut Vec<Token>,
+) -> Result<(), LexError> {
+    let mut chars = line.char_indices().peekable();
+
+    let span = |start: usize, end: usize| Span {
+        file: file.to_string(),
+        line: line_number,
+        column: line[..start].chars().count() + 1,
+        start,
+        end,
+    };
+    let error = |kind: LexErrorKind, start: usize, end: usize| LexError {
+        kind,
+        span: span(start, end),
+    };
+
+    while let Some(&(start, ch)) = chars.peek() {
         if ch.is_whitespace() {
             chars.next();
         } else if ch == '#' {
@@ -32,53 +257,87 @@ pub fn get_tokens(line: &str) -> (Vec<crate::types::Token>, u8) {
         } else if ch == '\'' {
             chars.next();
 
-            match chars.next() {
-                Some('\\') => match chars.next() {
-                    Some(c) => match get_escape_sequence(c) {
-                        Some(escaped) => tokens.push(Literal(Character(escaped))),
-                        None => panic!("Invalid escape sequence: \\{}", c),
+            let character = match chars.next() {
+                Some((escape, '\\')) => match chars.next() {
+                    Some((i, c)) => match get_escape_sequence(c) {
+                        Some(escaped) => escaped,
+                        None => {
+                            return Err(error(InvalidEscapeSequence(c), escape, i + c.len_utf8()));
+                        }
                     },
-                    None => panic!("Unterminated character literal"),
+                    None => return Err(error(UnterminatedCharacter, start, line.len())),
                 },
-                Some(c) => {
-                    if chars.next() == Some('\'') {
-                        tokens.push(Literal(Character(c.to_string())));
-                    } else {
-                        panic!("Unterminated character literal");
-                    }
-                }
-                None => panic!("Unterminated character literal"),
+                Some((i, '\'')) => return Err(error(EmptyCharacter, start, i + 1)),
+                Some((_, c)) => c.to_string(),
+                None => return Err(error(UnterminatedCharacter, start, line.len())),
+            };
+
+            match chars.next() {
+                Some((i, '\'')) => tokens.push(Token {
+                    kind: Literal(Character(character)),
+                    span: span(start, i + 1),
+                }),
+                Some((i, _)) => return Err(error(UnterminatedCharacter, start, i)),
+                None => return Err(error(UnterminatedCharacter, start, line.len())),
             }
         } else if ch == '"' {
             chars.next();
             let mut literal = String::new();
-            let mut is_escaped = false;
-            let mut is_terminated = false;
+            let mut end = None;
 
-            while let Some(c) = chars.next() {
+            while let Some((i, c)) = chars.next() {
                 match c {
-                    '\\' if !is_escaped => is_escaped = true,
-                    '"' if !is_escaped => {
-                        is_terminated = true;
+                    '\\' => match chars.next() {
+                        Some((j, e)) => match get_escape_sequence(e) {
+                            Some(escaped) => literal.push_str(&escaped),
+                            None => {
+                                return Err(error(InvalidEscapeSequence(e), i, j + e.len_utf8()));
+                            }
+                        },
+                        None => break,
+                    },
+                    '"' => {
+                        end = Some(i + 1);
                         break;
                     }
                     _ => literal.push(c),
                 }
             }
 
-            if !is_terminated {
-                panic!("Unterminated string literal");
+            match end {
+                Some(end) => tokens.push(Token {
+                    kind: Literal(Str(literal)),
+                    span: span(start, end),
+                }),
+                None => return Err(error(UnterminatedString, start, line.len())),
             }
-
-            tokens.push(Literal(Str(literal)));
         } else if let Some(token) = get_special_character(ch) {
             chars.next();
-            tokens.push(SpecialCharacter(token));
+
+            let compound = chars
+                .peek()
+                .and_then(|&(_, next)| get_compound_operator(ch, next));
+            let (token, end) = match compound {
+                Some(DoubleDot) if chars.clone().nth(1).map(|(_, next)| next) == Some('.') => {
+                    chars.nth(1);
+                    (Ellipsis, start + 3)
+                }
+                Some(compound) => {
+                    chars.next();
+                    (compound, start + 2)
+                }
+                None => (token, start + 1),
+            };
+
+            tokens.push(Token {
+                kind: SpecialCharacter(token),
+                span: span(start, end),
+            });
         } else {
             let mut word = String::new();
             let mut seen_dot = false;
 
-            while let Some(&c) = chars.peek() {
+            while let Some(&(_, c)) = chars.peek() {
                 if c.is_ascii_digit() {
                     word.push(c);
                     chars.next();
@@ -89,7 +348,7 @@ pub fn get_tokens(line: &str) -> (Vec<crate::types::Token>, u8) {
                 {
                     let mut peek_chars = chars.clone();
                     peek_chars.next();
-                    if let Some(&next_ch) = peek_chars.peek() {
+                    if let Some(&(_, next_ch)) = peek_chars.peek() {
                         if next_ch == '.' {
                             break;
                         } else if next_ch.is_ascii_digit() {
@@ -110,21 +369,28 @@ pub fn get_tokens(line: &str) -> (Vec<crate::types::Token>, u8) {
                 }
             }
 
-            if let Some(keyword) = get_keyword(&word) {
-                tokens.push(Keyword(keyword));
+            let end = start + word.len();
+
+            let kind = if let Some(keyword) = get_keyword(&word) {
+                Keyword(keyword)
             } else if word.parse::<f64>().is_ok() {
-                tokens.push(Literal(Number(word)));
+                Literal(Number(word))
             } else if word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
                 && !word.chars().next().unwrap_or('0').is_ascii_digit()
             {
-                tokens.push(Identifier(word));
+                Identifier(word)
             } else {
-                panic!("Unexpected token: {}", word);
-            }
+                return Err(error(UnexpectedToken(word), start, end));
+            };
+
+            tokens.push(Token {
+                kind,
+                span: span(start, end),
+            });
         }
     }
 
-    (tokens, spaces / 4)
+    Ok(())
 }
 
 fn is_special_character(ch: char) -> bool {
@@ -151,6 +417,28 @@ fn get_special_character(ch: char) -> Option<crate::types::SpecialCharacter> {
         ']' => Some(CloseBracket),
         '{' => Some(OpenBrace),
         '}' => Some(CloseBrace),
+        '\\' => Some(Backslash),
+        _ => None,
+    }
+}
+
+fn get_compound_operator(first: char, second: char) -> Option<crate::types::SpecialCharacter> {
+    match (first, second) {
+        ('=', '=') => Some(DoubleEquals),
+        ('!', '=') => Some(NotEquals),
+        ('<', '=') => Some(SmallerThanOrEquals),
+        ('>', '=') => Some(LargerThanOrEquals),
+        ('+', '=') => Some(PlusEquals),
+        ('-', '=') => Some(MinusEquals),
+        ('*', '=') => Some(AsteriskEquals),
+        ('/', '=') => Some(SlashEquals),
+        ('%', '=') => Some(PercentEquals),
+        (':', ':') => Some(DoubleColon),
+        ('.', '.') => Some(DoubleDot),
+        ('+', '+') => Some(DoublePlus),
+        ('-', '-') => Some(DoubleMinus),
+        ('-', '>') => Some(Arrow),
+        ('=', '>') => Some(FatArrow),
         _ => None,
     }
 }
@@ -160,12 +448,19 @@ fn get_keyword(word: &str) -> Option<crate::types::Keyword> {
         "let" => Some(Let),
         "const" => Some(Const),
         "method" => Some(Method),
+        "struct" => Some(Struct),
         "return" => Some(Return),
         "import" => Some(Import),
         "if" => Some(If),
         "else" => Some(Else),
         "loop" => Some(Loop),
         "in" => Some(In),
+        "match" => Some(Match),
+        "try" => Some(Try),
+        "catch" => Some(Catch),
+        "finally" => Some(Finally),
+        "throw" => Some(Throw),
+        "yield" => Some(Yield),
         "true" => Some(True),
         "false" => Some(False),
         "null" => Some(Null),
@@ -183,9 +478,10 @@ fn get_escape_sequence(ch: char) -> Option<String> {
         'n' => Some("\n".to_string()),
         't' => Some("\t".to_string()),
         'r' => Some("\r".to_string()),
+        '0' => Some("\0".to_string()),
         '\\' => Some("\\".to_string()),
         '\'' => Some("'".to_string()),
-        '\0' => Some("\0".to_string()),
+        '"' => Some("\"".to_string()),
         _ => None,
     }
 }
@@ -0,0 +1,597 @@
+use crate::ast::*;
+use crate::checker::get_literal_type;
+use crate::generator::BUILTINS;
+use crate::types::{Diagnostic, Span};
+use std::collections::HashMap;
+
+pub const LINTS: [&str; 6] = [
+    "unused_variable",
+    "let_could_be_const",
+    "shadowed_builtin",
+    "unreachable_code",
+    "unreachable_arm",
+    "null_comparison",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// How each lint is reported. Lints are warnings unless the `lints` object
+/// in `lyra.json` sets them to `allow`, `warn` or `deny`.
+pub struct Levels(HashMap<&'static str, Level>);
+
+impl Levels {
+    pub fn load() -> Result<Levels, String> {
+        let mut levels = Levels(LINTS.iter().map(|&name| (name, Level::Warn)).collect());
+
+        let Ok(content) = std::fs::read_to_string("lyra.json") else {
+            return Ok(levels);
+        };
+        let config: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|error| format!("failed to parse lyra.json: {error}"))?;
+        let Some(lints) = config.get("lints") else {
+            return Ok(levels);
+        };
+        let lints = lints
+            .as_object()
+            .ok_or("`lints` in lyra.json must be an object")?;
+
+        for (name, level) in lints {
+            let Some(&name) = LINTS.iter().find(|&lint| lint == name) else {
+                return Err(format!(
+                    "unknown lint `{name}` in lyra.json, expected one of: {}",
+                    LINTS.join(", ")
+                ));
+            };
+            let level = match level.as_str() {
+                Some("allow") => Level::Allow,
+                Some("warn") => Level::Warn,
+                Some("deny") => Level::Deny,
+                _ => {
+                    return Err(format!(
+                        "lint `{name}` must be set to \"allow\", \"warn\" or \"deny\", found {level}"
+                    ));
+                }
+            };
+            levels.0.insert(name, level);
+        }
+
+        Ok(levels)
+    }
+}
+
+/// Runs the lints over every module. Each report ends with the name of its
+/// lint, so it can be looked up in `lyra.json`.
+pub fn lint(modules: &[Module], levels: &Levels) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for module in modules {
+        let mut linter = Linter {
+            levels,
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        linter.lint_module(module);
+        diagnostics.extend(linter.diagnostics);
+    }
+
+    diagnostics
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// A `let` whose uses are checked when its scope ends.
+    Local { has_value: bool },
+    /// Any other name, which is only tracked so it can hide locals.
+    Other,
+}
+
+struct Variable<'a> {
+    kind: Kind,
+    span: &'a Span,
+    read: bool,
+    assigned: bool,
+}
+
+struct Linter<'a> {
+    levels: &'a Levels,
+    scopes: Vec<HashMap<&'a str, Variable<'a>>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn lint_module(&mut self, module: &'a Module) {
+        self.scopes.push(HashMap::new());
+        // The variables and constants of `main.ly` are locals of `main()`,
+        // and are linted like any other, while module variables can be read
+        // and assigned from other modules, so they are never reported.
+        let main = module.name == "main";
+
+        for statement in &module.statements {
+            match &statement.kind {
+                StatementKind::Let { name, .. } | StatementKind::Const { name, .. } if !main => {
+                    self.declare(name, Kind::Other, &statement.span)
+                }
+                StatementKind::Function { name, .. } | StatementKind::Struct { name, .. } => {
+                    self.check_builtin(name, &statement.span);
+                    self.declare(name, Kind::Other, &statement.span);
+                }
+                _ => {}
+            }
+        }
+
+        // Functions are generated outside of `main()`, so they are linted
+        // before its locals are declared.
+        for statement in &module.statements {
+            match &statement.kind {
+                StatementKind::Let { value, .. } if !main => self.lint_optional(value.as_ref()),
+                StatementKind::Const { value, .. } if !main => self.lint_expression(value),
+                StatementKind::Function { params, body, .. }
+                | StatementKind::Method { params, body, .. } => self.lint_function(params, body),
+                _ => {}
+            }
+        }
+
+        if main {
+            self.lint_block(module.statements.iter().filter(|statement| {
+                !matches!(
+                    statement.kind,
+                    StatementKind::Function { .. }
+                        | StatementKind::Method { .. }
+                        | StatementKind::Struct { .. }
+                )
+            }));
+        }
+
+        self.close_scope();
+    }
+
+    fn lint_function(&mut self, params: &'a [Parameter], body: &'a Block) {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.lint_optional(param.default.as_ref());
+            self.check_builtin(&param.name, &param.span);
+            self.declare(&param.name, Kind::Other, &param.span);
+        }
+        self.lint_statements(body);
+        self.close_scope();
+    }
+
+    fn lint_block(&mut self, block: impl IntoIterator<Item = &'a Statement>) {
+        self.scopes.push(HashMap::new());
+        self.lint_statements(block);
+        self.close_scope();
+    }
+
+    fn lint_statements(&mut self, statements: impl IntoIterator<Item = &'a Statement>) {
+        let mut exit = None;
+        let mut reported = false;
+
+        for statement in statements {
+            // Only the first unreachable statement of a block is reported.
+            if let Some(span) = exit
+                && !reported
+            {
+                self.report(
+                    "unreachable_code",
+                    Diagnostic::warning("unreachable statement", &statement.span)
+                        .with_note("any code after this statement is unreachable", span),
+                );
+                reported = true;
+            }
+
+            self.lint_statement(statement);
+
+            if exit.is_none()
+                && matches!(
+                    statement.kind,
+                    StatementKind::Return(_)
+                        | StatementKind::Throw(_)
+                        | StatementKind::Break
+                        | StatementKind::Continue
+                )
+            {
+                exit = Some(&statement.span);
+            }
+        }
+    }
+
+    fn lint_statement(&mut self, statement: &'a Statement) {
+        match &statement.kind {
+            StatementKind::Let { name, value, .. } => {
+                self.lint_optional(value.as_ref());
+                self.check_builtin(name, &statement.span);
+                self.declare(
+                    name,
+                    Kind::Local {
+                        has_value: value.is_some(),
+                    },
+                    &statement.span,
+                );
+            }
+            StatementKind::Const { name, value, .. } => {
+                self.lint_expression(value);
+                self.check_builtin(name, &statement.span);
+                self.declare(name, Kind::Other, &statement.span);
+            }
+            StatementKind::Assign { target, value, .. } => {
+                self.lint_expression(value);
+                self.assign(target);
+            }
+            StatementKind::Update { target, .. } => self.assign(target),
+            StatementKind::Expression(expression) => self.lint_expression(expression),
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for (condition, body) in branches {
+                    self.lint_expression(condition);
+                    self.lint_block(body);
+                }
+                if let Some(body) = else_body {
+                    self.lint_block(body);
+                }
+            }
+            StatementKind::Loop { body } => self.lint_block(body),
+            StatementKind::While { condition, body } => {
+                self.lint_expression(condition);
+                self.lint_block(body);
+            }
+            StatementKind::For {
+                variable,
+                value_variable,
+                iterable,
+                body,
+            } => {
+                self.lint_expression(iterable);
+                self.scopes.push(HashMap::new());
+                for variable in std::iter::once(variable).chain(value_variable) {
+                    self.check_builtin(variable, &statement.span);
+                    self.declare(variable, Kind::Other, &statement.span);
+                }
+                self.lint_statements(body);
+                self.close_scope();
+            }
+            StatementKind::Match { value, arms } => {
+                self.lint_expression(value);
+                self.check_arms(arms);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    for name in arm.pattern.bindings() {
+                        self.check_builtin(name, &arm.span);
+                        self.declare(name, Kind::Other, &arm.span);
+                    }
+                    self.lint_optional(arm.guard.as_ref());
+                    self.lint_statements(&arm.body);
+                    self.close_scope();
+                }
+            }
+            StatementKind::Try {
+                body,
+                variable,
+                catch_body,
+                finally_body,
+            } => {
+                self.lint_block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = variable {
+                        self.check_builtin(variable, &statement.span);
+                        self.declare(variable, Kind::Other, &statement.span);
+                    }
+                    self.lint_statements(catch_body);
+                    self.close_scope();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.lint_block(finally_body);
+                }
+            }
+            StatementKind::Throw(value) | StatementKind::Yield(value) => {
+                self.lint_expression(value)
+            }
+            StatementKind::Break | StatementKind::Continue => {}
+            StatementKind::Return(value) => self.lint_optional(value.as_ref()),
+            StatementKind::Function { .. }
+            | StatementKind::Method { .. }
+            | StatementKind::Struct { .. } => {
+                unreachable!("Definitions only appear at the top level of a file")
+            }
+        }
+    }
+
+    fn lint_optional(&mut self, expression: Option<&'a Expression>) {
+        if let Some(expression) = expression {
+            self.lint_expression(expression);
+        }
+    }
+
+    fn lint_expression(&mut self, expression: &'a Expression) {
+        match &expression.kind {
+            // Names of built-in functions always refer to the built-in.
+            ExpressionKind::Identifier(name) if !BUILTINS.contains(&name.as_str()) => {
+                if let Some(variable) = self.lookup(name) {
+                    variable.read = true;
+                }
+            }
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.lint_expression(item);
+                }
+            }
+            ExpressionKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.lint_expression(key);
+                    self.lint_expression(value);
+                }
+            }
+            ExpressionKind::Range { start, end } => {
+                self.lint_optional(start.as_deref());
+                self.lint_optional(end.as_deref());
+            }
+            ExpressionKind::Unary { operand, .. } => self.lint_expression(operand),
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                if matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual)
+                    && [left, right]
+                        .iter()
+                        .any(|operand| matches!(operand.kind, ExpressionKind::Null))
+                {
+                    self.report(
+                        "null_comparison",
+                        Diagnostic::warning(
+                            format!(
+                                "comparison with `null` using `{}`; use `type(value) {} \"null\"` instead",
+                                operator.symbol(),
+                                operator.symbol()
+                            ),
+                            &expression.span,
+                        ),
+                    );
+                }
+                self.lint_expression(left);
+                self.lint_expression(right);
+            }
+            ExpressionKind::Call {
+                callee, arguments, ..
+            } => {
+                self.lint_expression(callee);
+                for argument in arguments {
+                    self.lint_expression(argument);
+                }
+            }
+            ExpressionKind::Index { object, index } => {
+                self.lint_expression(object);
+                self.lint_expression(index);
+            }
+            ExpressionKind::Member { object, .. } => self.lint_expression(object),
+            ExpressionKind::Lambda { params, body } => self.lint_function(params, body),
+            ExpressionKind::Identifier(_)
+            | ExpressionKind::Number(_)
+            | ExpressionKind::Str(_)
+            | ExpressionKind::Character(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Null
+            | ExpressionKind::ModuleMember { .. } => {}
+        }
+    }
+
+    /// Records an assignment. Assigning to a variable doesn't read it, but
+    /// assigning to one of its elements or fields does.
+    fn assign(&mut self, target: &'a Expression) {
+        let mut root = target;
+        loop {
+            match &root.kind {
+                ExpressionKind::Index { object, index } => {
+                    self.lint_expression(index);
+                    root = object;
+                }
+                ExpressionKind::Member { object, .. } => root = object,
+                _ => break,
+            }
+        }
+
+        if let ExpressionKind::Identifier(name) = &root.kind
+            && let Some(variable) = self.lookup(name)
+        {
+            variable.assigned = true;
+            if !std::ptr::eq(root, target) {
+                variable.read = true;
+            }
+        }
+    }
+
+    /// Reports arms that can never run, because every value they match is
+    /// matched first by an earlier arm without a guard.
+    fn check_arms(&mut self, arms: &[Arm]) {
+        for (i, arm) in arms.iter().enumerate() {
+            if let Some(earlier) = arms[..i]
+                .iter()
+                .find(|earlier| earlier.guard.is_none() && covers(&earlier.pattern, &arm.pattern))
+            {
+                self.report(
+                    "unreachable_arm",
+                    Diagnostic::warning("unreachable arm", &arm.span).with_note(
+                        "every value it matches is matched by this arm",
+                        &earlier.span,
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_builtin(&mut self, name: &str, span: &Span) {
+        if BUILTINS.contains(&name) {
+            self.report(
+                "shadowed_builtin",
+                Diagnostic::warning(
+                    format!(
+                        "`{name}` has the name of a built-in function, which every use of `{name}` still calls"
+                    ),
+                    span,
+                ),
+            );
+        }
+    }
+
+    fn declare(&mut self, name: &'a str, kind: Kind, span: &'a Span) {
+        self.scopes
+            .last_mut()
+            .expect("A scope is always open")
+            .insert(
+                name,
+                Variable {
+                    kind,
+                    span,
+                    read: false,
+                    assigned: false,
+                },
+            );
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Variable<'a>> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// Ends the innermost scope, reporting the locals declared in it that
+    /// were never read or never reassigned. Names starting with `_` are
+    /// meant to go unused.
+    fn close_scope(&mut self) {
+        let scope = self.scopes.pop().expect("A scope is always open");
+        let mut variables = scope.into_iter().collect::<Vec<_>>();
+        variables.sort_by_key(|(_, variable)| (variable.span.line, variable.span.column));
+
+        for (name, variable) in variables {
+            let Kind::Local { has_value } = variable.kind else {
+                continue;
+            };
+
+            if !variable.read && !name.starts_with('_') {
+                let message = if variable.assigned {
+                    format!("variable `{name}` is assigned to, but never read")
+                } else {
+                    format!("unused variable `{name}`")
+                };
+                self.report(
+                    "unused_variable",
+                    Diagnostic::warning(message, variable.span),
+                );
+            } else if !variable.assigned && has_value {
+                self.report(
+                    "let_could_be_const",
+                    Diagnostic::warning(
+                        format!("variable `{name}` is never reassigned, so it could be a `const`"),
+                        variable.span,
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Reports a lint at the level the project sets for it.
+    fn report(&mut self, lint: &'static str, mut diagnostic: Diagnostic) {
+        match self.levels.0[lint] {
+            Level::Allow => return,
+            Level::Warn => {}
+            Level::Deny => diagnostic.severity = crate::types::Severity::Error,
+        }
+        diagnostic.message = format!("{} [{lint}]", diagnostic.message);
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// Whether every value `later` matches is also matched by `earlier`. This
+/// errs towards `false`, so only arms that surely can't run are reported.
+fn covers(earlier: &Pattern, later: &Pattern) -> bool {
+    match (earlier, later) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Type(ty), Pattern::Type(other)) => ty == other,
+        (Pattern::Type(Type::List), Pattern::List { .. }) => true,
+        // A number literal matches ints and floats alike, so no one type
+        // covers it.
+        (Pattern::Type(ty), Pattern::Literal(literal)) => {
+            !matches!(literal.kind, ExpressionKind::Number(_))
+                && get_literal_type(literal) == Some(*ty)
+        }
+        (Pattern::Literal(literal), Pattern::Literal(other)) => {
+            match (&literal.kind, &other.kind) {
+                (ExpressionKind::Str(l), ExpressionKind::Str(r))
+                | (ExpressionKind::Character(l), ExpressionKind::Character(r)) => l == r,
+                (ExpressionKind::Bool(l), ExpressionKind::Bool(r)) => l == r,
+                (ExpressionKind::Null, ExpressionKind::Null) => true,
+                // Ints and floats are compared by value, so `1` matches `1.0`.
+                (ExpressionKind::Number(l), ExpressionKind::Number(r)) => {
+                    l.parse::<f64>().ok() == r.parse::<f64>().ok()
+                }
+                _ => false,
+            }
+        }
+        (Pattern::Range { start, end }, Pattern::Literal(literal)) => {
+            match (get_bounds(start, end), get_number(literal)) {
+                (Some((low, high, is_char)), Some((n, is_char_literal))) => {
+                    is_char == is_char_literal && low <= n && n < high
+                }
+                _ => false,
+            }
+        }
+        (
+            Pattern::Range { start, end },
+            Pattern::Range {
+                start: low,
+                end: high,
+            },
+        ) => match (get_bounds(start, end), get_bounds(low, high)) {
+            (Some((low, high, is_char)), Some((other_low, other_high, other_is_char))) => {
+                is_char == other_is_char && low <= other_low && other_high <= high
+            }
+            _ => false,
+        },
+        (
+            Pattern::List { items, rest },
+            Pattern::List {
+                items: others,
+                rest: other_rest,
+            },
+        ) => match (rest, other_rest) {
+            (Some(_), _) => items.len() <= others.len(),
+            (None, None) => items.len() == others.len(),
+            (None, Some(_)) => false,
+        },
+        _ => false,
+    }
+}
+
+/// The bounds of a range pattern, with missing ones unbounded, and whether
+/// it is a range of characters.
+fn get_bounds(start: &Option<Expression>, end: &Option<Expression>) -> Option<(f64, f64, bool)> {
+    let is_char = start
+        .iter()
+        .chain(end)
+        .any(|bound| matches!(bound.kind, ExpressionKind::Character(_)));
+    let low = start
+        .as_ref()
+        .map_or(Some(f64::NEG_INFINITY), |start| Some(get_number(start)?.0))?;
+    let high = end
+        .as_ref()
+        .map_or(Some(f64::INFINITY), |end| Some(get_number(end)?.0))?;
+    Some((low, high, is_char))
+}
+
+/// The value of a number or character literal, and whether it is a
+/// character.
+fn get_number(literal: &Expression) -> Option<(f64, bool)> {
+    match &literal.kind {
+        ExpressionKind::Number(n) => Some((n.parse().ok()?, false)),
+        ExpressionKind::Character(c) => Some((c.chars().next()? as u32 as f64, true)),
+        _ => None,
+    }
+}
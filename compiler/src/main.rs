@@ -1,9 +1,19 @@
+mod ast;
+mod checker;
 mod compiler;
 mod extra;
+mod flow;
 mod generator;
+mod imports;
+mod infer;
 mod input;
 mod lexer;
+mod lints;
+mod optimizer;
+mod parser;
+mod resolver;
 mod stdlib;
+mod symbols;
 mod types;
 
 fn main() {
@@ -63,6 +73,10 @@ fn main() {
             }
             _ => panic!("Too many arguments for build"),
         },
+        "lint" => match args.len() {
+            0 => compiler::lint(&input::get_filenames()),
+            _ => panic!("Too many arguments for lint"),
+        },
         "run" => match args.len() {
             0 => {
                 extra::clean();
@@ -0,0 +1,521 @@
+use crate::ast::*;
+use std::collections::{HashMap, HashSet};
+
+/// Simplifies the program before it is generated. Arithmetic, string
+/// concatenation, comparisons and logic on literals are worked out ahead of
+/// time, top-level constants with literal values are replaced by their
+/// values, and branches whose conditions are known are dropped. Anything
+/// the runtime would reject, like dividing by zero, is left for it to
+/// report.
+pub fn optimize(modules: &mut [Module]) {
+    let constants = get_constants(modules);
+
+    for module in modules {
+        let mut optimizer = Optimizer {
+            module: module.name.clone(),
+            constants: &constants,
+            scopes: Vec::new(),
+        };
+        optimizer.optimize_module(module);
+    }
+}
+
+type Constants = HashMap<(String, String), ExpressionKind>;
+
+/// Finds the top-level constants whose values fold to literals. Constants
+/// can be defined in terms of each other, so this repeats until no more
+/// are found.
+fn get_constants(modules: &[Module]) -> Constants {
+    let mut constants = Constants::new();
+
+    loop {
+        let mut found = Vec::new();
+
+        for module in modules {
+            let mut optimizer = Optimizer {
+                module: module.name.clone(),
+                constants: &constants,
+                scopes: Vec::new(),
+            };
+
+            for statement in &module.statements {
+                if let StatementKind::Const { name, value, .. } = &statement.kind
+                    && !constants.contains_key(&(module.name.clone(), name.clone()))
+                {
+                    let mut value = value.clone();
+                    optimizer.optimize_expression(&mut value);
+                    if get_constant(&value).is_some() {
+                        found.push(((module.name.clone(), name.clone()), value.kind));
+                    }
+                }
+            }
+        }
+
+        if found.is_empty() {
+            return constants;
+        }
+        constants.extend(found);
+    }
+}
+
+struct Optimizer<'a> {
+    module: String,
+    constants: &'a Constants,
+    /// Names declared in the blocks being optimized, which hide top-level
+    /// constants of the same name.
+    scopes: Vec<HashSet<String>>,
+}
+
+impl Optimizer<'_> {
+    fn optimize_module(&mut self, module: &mut Module) {
+        // Functions are generated outside of `main()`, so the constants of
+        // `main.ly` don't reach them.
+        let locals = module
+            .statements
+            .iter()
+            .filter_map(|statement| match &statement.kind {
+                StatementKind::Const { name, .. } if module.name == "main" => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        module
+            .statements
+            .retain_mut(|statement| match &mut statement.kind {
+                StatementKind::Function { params, body, .. }
+                | StatementKind::Method { params, body, .. } => {
+                    self.scopes.push(locals.clone());
+                    self.open_params(params);
+                    self.optimize_statements(body);
+                    self.scopes.truncate(self.scopes.len() - 2);
+                    true
+                }
+                _ => self.optimize_statement(statement),
+            });
+    }
+
+    /// Opens a scope with the parameters, and optimizes their defaults in
+    /// it.
+    fn open_params(&mut self, params: &mut [Parameter]) {
+        self.scopes
+            .push(params.iter().map(|param| param.name.clone()).collect());
+        for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+            self.optimize_expression(default);
+        }
+    }
+
+    fn optimize_block(&mut self, block: &mut Block) {
+        self.scopes.push(HashSet::new());
+        self.optimize_statements(block);
+        self.scopes.pop();
+    }
+
+    fn optimize_statements(&mut self, statements: &mut Vec<Statement>) {
+        statements.retain_mut(|statement| self.optimize_statement(statement));
+    }
+
+    /// Optimizes a statement, returning whether it should be kept.
+    fn optimize_statement(&mut self, statement: &mut Statement) -> bool {
+        match &mut statement.kind {
+            StatementKind::Let { name, value, .. } => {
+                if let Some(value) = value {
+                    self.optimize_expression(value);
+                }
+                self.declare(name);
+            }
+            StatementKind::Const { name, value, .. } => {
+                self.optimize_expression(value);
+                self.declare(name);
+            }
+            // Targets are left alone, since constants can't be assigned to.
+            StatementKind::Assign { value, .. } => self.optimize_expression(value),
+            StatementKind::Update { .. } | StatementKind::Break | StatementKind::Continue => {}
+            StatementKind::Expression(expression) => self.optimize_expression(expression),
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                let mut kept = Vec::new();
+
+                for (mut condition, mut body) in std::mem::take(branches) {
+                    self.optimize_expression(&mut condition);
+                    match get_constant(&condition).map(|condition| condition.is_truthy()) {
+                        Some(false) => continue,
+                        // Later branches can't be reached, so this one
+                        // takes the place of the `else`.
+                        Some(true) => {
+                            *else_body = Some(body);
+                            break;
+                        }
+                        None => {}
+                    }
+                    self.optimize_block(&mut body);
+                    kept.push((condition, body));
+                }
+
+                if let Some(body) = else_body {
+                    self.optimize_block(body);
+                }
+                // An `if` with only its `else` left is generated as a plain
+                // block.
+                if kept.is_empty() && else_body.is_none() {
+                    return false;
+                }
+                *branches = kept;
+            }
+            StatementKind::Loop { body } => self.optimize_block(body),
+            StatementKind::While { condition, body } => {
+                self.optimize_expression(condition);
+                match get_constant(condition).map(|condition| condition.is_truthy()) {
+                    Some(false) => return false,
+                    Some(true) => {
+                        statement.kind = StatementKind::Loop {
+                            body: std::mem::take(body),
+                        };
+                        return self.optimize_statement(statement);
+                    }
+                    None => self.optimize_block(body),
+                }
+            }
+            StatementKind::For {
+                variable,
+                value_variable,
+                iterable,
+                body,
+            } => {
+                self.optimize_expression(iterable);
+                self.scopes.push(
+                    std::iter::once(&*variable)
+                        .chain(value_variable.as_ref())
+                        .cloned()
+                        .collect(),
+                );
+                self.optimize_statements(body);
+                self.scopes.pop();
+            }
+            StatementKind::Match { value, arms } => {
+                self.optimize_expression(value);
+                arms.retain_mut(|arm| {
+                    self.scopes.push(
+                        arm.pattern
+                            .bindings()
+                            .into_iter()
+                            .map(str::to_string)
+                            .collect(),
+                    );
+                    // Arms whose guards never hold are dropped, and guards
+                    // that always do are left out.
+                    if let Some(guard) = &mut arm.guard {
+                        self.optimize_expression(guard);
+                        match get_constant(guard).map(|guard| guard.is_truthy()) {
+                            Some(false) => {
+                                self.scopes.pop();
+                                return false;
+                            }
+                            Some(true) => arm.guard = None,
+                            None => {}
+                        }
+                    }
+                    self.optimize_statements(&mut arm.body);
+                    self.scopes.pop();
+                    true
+                });
+            }
+            StatementKind::Try {
+                body,
+                variable,
+                catch_body,
+                finally_body,
+            } => {
+                self.optimize_block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(variable.iter().cloned().collect());
+                    self.optimize_statements(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.optimize_block(finally_body);
+                }
+            }
+            StatementKind::Throw(value) | StatementKind::Yield(value) => {
+                self.optimize_expression(value)
+            }
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.optimize_expression(value);
+                }
+            }
+            StatementKind::Struct { name, .. } => self.declare(name),
+            StatementKind::Function { .. } | StatementKind::Method { .. } => {
+                unreachable!("Definitions only appear at the top level of a file")
+            }
+        }
+
+        true
+    }
+
+    fn optimize_expression(&mut self, expression: &mut Expression) {
+        match &mut expression.kind {
+            ExpressionKind::Identifier(name) => {
+                if !self.is_declared(name)
+                    && let Some(value) = self.constants.get(&(self.module.clone(), name.clone()))
+                {
+                    expression.kind = value.clone();
+                }
+            }
+            ExpressionKind::ModuleMember { module, name } => {
+                if let Some(value) = self.constants.get(&(module.clone(), name.clone())) {
+                    expression.kind = value.clone();
+                }
+            }
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.optimize_expression(item);
+                }
+            }
+            ExpressionKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.optimize_expression(key);
+                    self.optimize_expression(value);
+                }
+            }
+            ExpressionKind::Range { start, end } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.optimize_expression(bound);
+                }
+            }
+            ExpressionKind::Unary { operator, operand } => {
+                self.optimize_expression(operand);
+                if let Some(operand) = get_constant(operand)
+                    && let Some(value) = fold_unary(*operator, operand)
+                {
+                    replace(expression, value);
+                }
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                self.optimize_expression(left);
+                self.optimize_expression(right);
+                if let Some(value) = fold_binary(*operator, get_constant(left), get_constant(right))
+                {
+                    replace(expression, value);
+                }
+            }
+            ExpressionKind::Call {
+                callee, arguments, ..
+            } => {
+                self.optimize_expression(callee);
+                for argument in arguments {
+                    self.optimize_expression(argument);
+                }
+            }
+            ExpressionKind::Index { object, index } => {
+                self.optimize_expression(object);
+                self.optimize_expression(index);
+            }
+            ExpressionKind::Member { object, .. } => self.optimize_expression(object),
+            ExpressionKind::Lambda { params, body } => {
+                self.open_params(params);
+                self.optimize_statements(body);
+                self.scopes.pop();
+            }
+            ExpressionKind::Number(_)
+            | ExpressionKind::Str(_)
+            | ExpressionKind::Character(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Null => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str) {
+        // Names at the top level of a module are the constants themselves,
+        // or can't share a name with one.
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string());
+        }
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+}
+
+/// A literal value, following the runtime's rules for it.
+enum Constant {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    Null,
+}
+
+impl Constant {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Constant::Str(s) => !s.is_empty(),
+            Constant::Null => false,
+            _ => self.get_number() != Some(0.0),
+        }
+    }
+
+    /// The number the runtime stores for a value, if it has one. Only
+    /// ASCII characters are numbers, since the runtime keeps characters in
+    /// a single byte.
+    fn get_number(&self) -> Option<f64> {
+        match self {
+            Constant::Int(n) => Some(f64::from(*n)),
+            Constant::Float(n) => Some(*n),
+            Constant::Bool(b) => Some(f64::from(u8::from(*b))),
+            Constant::Char(c) if c.is_ascii() => Some(f64::from(*c as u8)),
+            _ => None,
+        }
+    }
+
+    /// The text of a value, as the runtime joins it onto a string. Floats
+    /// are left out, so their formatting stays the runtime's job.
+    fn to_text(&self) -> Option<String> {
+        match self {
+            Constant::Int(n) => Some(n.to_string()),
+            Constant::Bool(b) => Some(b.to_string()),
+            Constant::Char(c) if c.is_ascii() => Some(c.to_string()),
+            Constant::Str(s) => Some(s.clone()),
+            Constant::Null => Some("null".to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn get_constant(expression: &Expression) -> Option<Constant> {
+    match &expression.kind {
+        ExpressionKind::Number(n) if n.contains('.') => n.parse().ok().map(Constant::Float),
+        ExpressionKind::Number(n) => n.parse().ok().map(Constant::Int),
+        ExpressionKind::Bool(b) => Some(Constant::Bool(*b)),
+        ExpressionKind::Character(c) => c.chars().next().map(Constant::Char),
+        ExpressionKind::Str(s) => Some(Constant::Str(s.clone())),
+        ExpressionKind::Null => Some(Constant::Null),
+        _ => None,
+    }
+}
+
+/// Replaces an expression with a folded value, if the value can be written
+/// as a literal.
+fn replace(expression: &mut Expression, value: Constant) {
+    let kind = match value {
+        Constant::Int(n) => ExpressionKind::Number(n.to_string()),
+        Constant::Float(n) => {
+            // Floats are written the shortest way that reads back the same,
+            // but that isn't a float literal if it lacks a `.` or uses an
+            // exponent.
+            let literal = format!("{n:?}");
+            if !n.is_finite() || !literal.contains('.') || literal.contains('e') {
+                return;
+            }
+            ExpressionKind::Number(literal)
+        }
+        Constant::Bool(b) => ExpressionKind::Bool(b),
+        Constant::Char(c) => ExpressionKind::Character(c.to_string()),
+        Constant::Str(s) => ExpressionKind::Str(s),
+        Constant::Null => ExpressionKind::Null,
+    };
+    expression.kind = kind;
+}
+
+fn fold_unary(operator: UnaryOperator, operand: Constant) -> Option<Constant> {
+    match (operator, operand) {
+        (UnaryOperator::Not, operand) => Some(Constant::Bool(!operand.is_truthy())),
+        (UnaryOperator::Negate, Constant::Int(n)) => n.checked_neg().map(Constant::Int),
+        (UnaryOperator::Negate, Constant::Float(n)) => Some(Constant::Float(-n)),
+        _ => None,
+    }
+}
+
+fn fold_binary(
+    operator: BinaryOperator,
+    left: Option<Constant>,
+    right: Option<Constant>,
+) -> Option<Constant> {
+    // `and` and `or` stop at their left operand when it settles the result,
+    // so the right one doesn't have to be known.
+    match (operator, left.as_ref().map(Constant::is_truthy)) {
+        (BinaryOperator::And, Some(false)) => return Some(Constant::Bool(false)),
+        (BinaryOperator::Or, Some(true)) => return Some(Constant::Bool(true)),
+        _ => {}
+    }
+    let (left, right) = (left?, right?);
+
+    match operator {
+        BinaryOperator::And | BinaryOperator::Or => Some(Constant::Bool(right.is_truthy())),
+        BinaryOperator::Equal => are_equal(&left, &right).map(Constant::Bool),
+        BinaryOperator::NotEqual => are_equal(&left, &right).map(|equal| Constant::Bool(!equal)),
+        BinaryOperator::Less
+        | BinaryOperator::Greater
+        | BinaryOperator::LessEqual
+        | BinaryOperator::GreaterEqual => {
+            let ordering = match (&left, &right) {
+                (Constant::Str(l), Constant::Str(r)) => l.cmp(r),
+                _ => left.get_number()?.partial_cmp(&right.get_number()?)?,
+            };
+            Some(Constant::Bool(match operator {
+                BinaryOperator::Less => ordering.is_lt(),
+                BinaryOperator::Greater => ordering.is_gt(),
+                BinaryOperator::LessEqual => ordering.is_le(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        BinaryOperator::Add
+            if matches!(left, Constant::Str(_)) || matches!(right, Constant::Str(_)) =>
+        {
+            Some(Constant::Str(left.to_text()? + &right.to_text()?))
+        }
+        BinaryOperator::In => None,
+        _ => fold_arithmetic(operator, left, right),
+    }
+}
+
+/// Folds arithmetic on two numbers. Ints are worked out in floating point
+/// and truncated, and only results that fit in 32 bits are kept, since for
+/// those this matches the runtime's wrapping 32-bit int arithmetic.
+fn fold_arithmetic(operator: BinaryOperator, left: Constant, right: Constant) -> Option<Constant> {
+    let is_float = matches!(left, Constant::Float(_)) || matches!(right, Constant::Float(_));
+    let (Constant::Int(_) | Constant::Float(_), Constant::Int(_) | Constant::Float(_)) =
+        (&left, &right)
+    else {
+        return None;
+    };
+    let (l, r) = (left.get_number()?, right.get_number()?);
+
+    let result = match operator {
+        BinaryOperator::Add => l + r,
+        BinaryOperator::Subtract => l - r,
+        BinaryOperator::Multiply => l * r,
+        BinaryOperator::Divide | BinaryOperator::Modulo if r == 0.0 => return None,
+        BinaryOperator::Divide => l / r,
+        BinaryOperator::Modulo => l - r * ((l / r) as i64 as f64),
+        _ => return None,
+    };
+
+    if is_float {
+        Some(Constant::Float(result))
+    } else {
+        let result = result.trunc();
+        (result >= f64::from(i32::MIN) && result <= f64::from(i32::MAX))
+            .then_some(Constant::Int(result as i32))
+    }
+}
+
+/// Whether two values are `==` in the runtime: numbers compare by value,
+/// strings by their text and `null` only equals itself.
+fn are_equal(left: &Constant, right: &Constant) -> Option<bool> {
+    match (left, right) {
+        (Constant::Str(l), Constant::Str(r)) => Some(l == r),
+        (Constant::Null, Constant::Null) => Some(true),
+        (Constant::Null | Constant::Str(_), _) | (_, Constant::Null | Constant::Str(_)) => {
+            Some(false)
+        }
+        _ => Some(left.get_number()? == right.get_number()?),
+    }
+}
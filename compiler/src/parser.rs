@@ -0,0 +1,1555 @@
+use crate::ast::*;
+use crate::types::Keyword::*;
+use crate::types::Literal::*;
+use crate::types::SpecialCharacter::*;
+use crate::types::TokenKind::*;
+use crate::types::{Line, ParseError, Span, Token, TokenKind};
+
+/// Parses the logical lines of a file. Errors are collected rather than
+/// returned early: a statement that fails to parse is reported and skipped
+/// together with any block indented under it, and parsing resumes at the
+/// next statement.
+pub fn parse(lines: &[Line], module: &str) -> (Module, Vec<ParseError>) {
+    let mut parser = Parser {
+        lines,
+        index: 0,
+        tokens: &[],
+        position: 0,
+        indent: 0,
+        module,
+        errors: Vec::new(),
+    };
+
+    let module = parser.parse_module();
+    (module, parser.errors)
+}
+
+struct Parser<'a> {
+    lines: &'a [Line],
+    index: usize,
+    tokens: &'a [Token],
+    position: usize,
+    /// The indentation of the statement being parsed, which the body of a
+    /// multi-line lambda is indented past.
+    indent: u8,
+    module: &'a str,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_module(&mut self) -> Module {
+        let mut imports = Vec::new();
+
+        while let Some(line) = self.lines.get(self.index) {
+            if !matches!(line.tokens.first(), Some(token) if token.kind == Keyword(Import)) {
+                break;
+            }
+
+            if line.has_errors {
+                self.index += 1;
+                continue;
+            }
+
+            match self.parse_import() {
+                Ok(import) => imports.push(import),
+                Err(error) => self.errors.push(error),
+            }
+        }
+
+        let statements = self.parse_block(0);
+
+        Module {
+            name: self.module.to_string(),
+            imports,
+            statements,
+        }
+    }
+
+    fn parse_import(&mut self) -> Result<crate::ast::Import, ParseError> {
+        self.start_line();
+        self.advance();
+        let span = self.previous_span();
+        let module = self.expect_identifier("module name")?;
+        self.expect_end()?;
+
+        if module == "std" || module == "main" {
+            return Err(ParseError {
+                message: format!("cannot import reserved module name: {module}"),
+                span: span.to(&self.previous_span()),
+            });
+        }
+
+        Ok(crate::ast::Import {
+            module,
+            span: span.to(&self.previous_span()),
+        })
+    }
+
+    fn parse_block(&mut self, indent: u8) -> Block {
+        let mut block = Vec::new();
+
+        while let Some(line) = self.lines.get(self.index) {
+            if line.indent < indent {
+                break;
+            }
+
+            if line.has_errors {
+                self.index += 1;
+                self.skip_body(line.indent);
+                continue;
+            }
+
+            if line.indent > indent {
+                self.errors.push(ParseError {
+                    message: "unexpected indentation".to_string(),
+                    span: line.tokens[0].span.clone(),
+                });
+                self.parse_block(line.indent);
+                continue;
+            }
+
+            let has_branches = matches!(
+                line.tokens[0].kind,
+                Keyword(If) | Keyword(Else) | Keyword(Try) | Keyword(Catch)
+            );
+
+            match self.parse_statement(indent) {
+                Ok(statement) => block.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.skip_body(indent);
+
+                    if has_branches {
+                        self.skip_branches(indent);
+                    }
+                }
+            }
+        }
+
+        block
+    }
+
+    /// Skips the `else` branches that followed an `if` that failed to parse,
+    /// or the `catch` and `finally` of a `try`, so they are not reported
+    /// again as having no matching `if` or `try`.
+    fn skip_branches(&mut self, indent: u8) {
+        while let Some(line) = self.lines.get(self.index)
+            && line.indent == indent
+            && matches!(
+                line.tokens.first(),
+                Some(token) if matches!(token.kind, Keyword(Else) | Keyword(Catch) | Keyword(Finally))
+            )
+        {
+            self.index += 1;
+            self.skip_body(indent);
+        }
+    }
+
+    /// Skips the block indented under a statement that failed to parse. The
+    /// block is still parsed so that errors inside it are reported too.
+    fn skip_body(&mut self, indent: u8) {
+        if let Some(line) = self.lines.get(self.index)
+            && line.indent > indent
+        {
+            self.parse_block(line.indent);
+        }
+    }
+
+    fn parse_body(&mut self, indent: u8, header: &Span) -> Result<Block, ParseError> {
+        match self.lines.get(self.index) {
+            Some(line) if line.indent > indent => Ok(self.parse_block(line.indent)),
+            _ => Err(ParseError {
+                message: "expected an indented block".to_string(),
+                span: header.clone(),
+            }),
+        }
+    }
+
+    fn parse_statement(&mut self, indent: u8) -> Result<Statement, ParseError> {
+        self.start_line();
+        self.parse_statement_here(indent)
+    }
+
+    /// Parses a statement from the current token, which for a `match` arm
+    /// is partway through its line.
+    fn parse_statement_here(&mut self, indent: u8) -> Result<Statement, ParseError> {
+        self.indent = indent;
+        let start = self.peek_span();
+        // `main.ly` mixes definitions in with its statements, so there a
+        // line is only a definition if it starts like one.
+        let top_level = indent == 0
+            && (self.module != "main"
+                || self.peek_kind() == Some(&Keyword(Method))
+                || self.at_function(0));
+
+        let kind = match self.peek_kind() {
+            Some(Keyword(Let)) => {
+                self.advance();
+                let name = self.expect_identifier("variable name")?;
+                let ty = self.parse_annotation()?;
+                let value = if self.at_end() {
+                    None
+                } else {
+                    self.expect(SpecialCharacter(Equals), "`=`")?;
+                    Some(self.parse_expression()?)
+                };
+                self.expect_end()?;
+                StatementKind::Let { name, ty, value }
+            }
+            Some(Keyword(Const)) => {
+                self.advance();
+                let name = self.expect_identifier("constant name")?;
+                let ty = self.parse_annotation()?;
+                self.expect(SpecialCharacter(Equals), "`=`")?;
+                let value = self.parse_expression()?;
+                self.expect_end()?;
+                StatementKind::Const { name, ty, value }
+            }
+            Some(Keyword(Struct)) if indent == 0 => self.parse_struct(&start)?,
+            Some(Keyword(Struct)) => {
+                return Err(
+                    self.error_here("structs can only be defined at the top level of a file")
+                );
+            }
+            _ if top_level => self.parse_definition(&start)?,
+            Some(Keyword(Method)) => {
+                return Err(
+                    self.error_here("methods can only be defined at the top level of a file")
+                );
+            }
+            _ if self.at_function(indent) => {
+                return Err(
+                    self.error_here("functions can only be defined at the top level of a file")
+                );
+            }
+            Some(Keyword(Return)) => {
+                self.advance();
+                let value = if self.at_end() {
+                    None
+                } else {
+                    Some(self.parse_expression()?)
+                };
+                self.expect_end()?;
+                StatementKind::Return(value)
+            }
+            Some(Keyword(Break)) => {
+                self.advance();
+                self.expect_end()?;
+                StatementKind::Break
+            }
+            Some(Keyword(Continue)) => {
+                self.advance();
+                self.expect_end()?;
+                StatementKind::Continue
+            }
+            Some(Keyword(If)) => self.parse_if(indent, &start)?,
+            Some(Keyword(Else)) => {
+                return Err(self.error_here("`else` without a matching `if`"));
+            }
+            Some(Keyword(Loop)) => self.parse_loop(indent, &start)?,
+            Some(Keyword(Match)) => self.parse_match(indent, &start)?,
+            Some(Keyword(Try)) => self.parse_try(indent, &start)?,
+            Some(Keyword(Catch)) => {
+                return Err(self.error_here("`catch` without a matching `try`"));
+            }
+            Some(Keyword(Finally)) => {
+                return Err(self.error_here("`finally` without a matching `try`"));
+            }
+            Some(Keyword(Throw)) => {
+                self.advance();
+                let value = self.parse_expression()?;
+                self.expect_end()?;
+                StatementKind::Throw(value)
+            }
+            Some(Keyword(Yield)) => {
+                self.advance();
+                let value = self.parse_expression()?;
+                self.expect_end()?;
+                StatementKind::Yield(value)
+            }
+            Some(SpecialCharacter(DoublePlus)) | Some(SpecialCharacter(DoubleMinus)) => {
+                let operator = self.parse_update_operator()?;
+                let target = self.parse_assignment_target()?;
+                self.expect_end()?;
+                StatementKind::Update {
+                    target,
+                    operator,
+                    prefix: true,
+                }
+            }
+            _ => self.parse_simple_statement()?,
+        };
+
+        Ok(Statement {
+            kind,
+            span: start.to(&self.previous_span()),
+        })
+    }
+
+    fn parse_definition(&mut self, start: &Span) -> Result<StatementKind, ParseError> {
+        let is_method = self.eat(&Keyword(Method));
+        let mut name = match self.peek_kind() {
+            Some(Identifier(_)) if is_method || self.at_function(0) => {
+                self.expect_identifier("function name")?
+            }
+            _ => {
+                return Err(self.error_here(
+                    "expected a variable, constant, function, method or struct definition at the top level of a module",
+                ));
+            }
+        };
+        let receiver = if is_method && self.eat(&SpecialCharacter(Dot)) {
+            Some(std::mem::replace(
+                &mut name,
+                self.expect_identifier("method name")?,
+            ))
+        } else {
+            None
+        };
+        let params = self.parse_params()?;
+        let return_type = if self.eat(&SpecialCharacter(Arrow)) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect_end()?;
+        let body = self.parse_body(0, &start.to(&self.previous_span()))?;
+
+        Ok(if is_method {
+            StatementKind::Method {
+                name,
+                receiver,
+                params,
+                return_type,
+                body,
+            }
+        } else {
+            StatementKind::Function {
+                name,
+                params,
+                return_type,
+                body,
+            }
+        })
+    }
+
+    /// Whether the line starts a function: a name and `(`, with a body
+    /// indented past `indent` on the next line. The lines of a lambda's body
+    /// in brackets come before the end of the line holding it, so they
+    /// aren't taken for one.
+    fn at_function(&self, indent: u8) -> bool {
+        let end = self.tokens.last().map(|token| token.span.line);
+        matches!(self.peek_kind(), Some(Identifier(_)))
+            && self.peek_kind_at(1) == Some(&SpecialCharacter(OpenParenthesis))
+            && matches!(
+                self.lines.get(self.index),
+                Some(line) if line.indent > indent
+                    && line.tokens.first().map(|token| token.span.line) > end
+            )
+    }
+
+    /// Parses a struct and the fields indented under it, one per line.
+    fn parse_struct(&mut self, start: &Span) -> Result<StatementKind, ParseError> {
+        self.advance();
+        let name = self.expect_identifier("struct name")?;
+        self.expect_end()?;
+
+        let indent = match self.lines.get(self.index) {
+            Some(line) if line.indent > 0 => line.indent,
+            _ => {
+                return Err(ParseError {
+                    message: "expected the struct's fields, indented one per line".to_string(),
+                    span: start.to(&self.previous_span()),
+                });
+            }
+        };
+
+        // The statement's span is taken from the header, so the fields are
+        // parsed without losing its place.
+        let (tokens, position) = (self.tokens, self.position);
+        let mut fields = Vec::new();
+
+        while let Some(line) = self.lines.get(self.index)
+            && line.indent >= indent
+        {
+            if line.has_errors {
+                self.index += 1;
+                continue;
+            }
+            if line.indent > indent {
+                self.errors.push(ParseError {
+                    message: "unexpected indentation".to_string(),
+                    span: line.tokens[0].span.clone(),
+                });
+                self.index += 1;
+                continue;
+            }
+
+            match self.parse_field() {
+                Ok(field) => fields.push(field),
+                Err(error) => self.errors.push(error),
+            }
+        }
+
+        self.tokens = tokens;
+        self.position = position;
+        Ok(StatementKind::Struct { name, fields })
+    }
+
+    fn parse_field(&mut self) -> Result<Parameter, ParseError> {
+        self.start_line();
+        let span = self.peek_span();
+        let name = self.expect_identifier("field name")?;
+        let ty = self.parse_annotation()?;
+        self.expect_end()?;
+        Ok(Parameter {
+            name,
+            ty,
+            default: None,
+            rest: false,
+            span,
+        })
+    }
+
+    /// Parses `(params)`, where a parameter can have a default, as in
+    /// `b = 10`, and the last one can be a rest parameter, as in `...rest`.
+    fn parse_params(&mut self) -> Result<Vec<Parameter>, ParseError> {
+        self.expect(SpecialCharacter(OpenParenthesis), "`(`")?;
+        let mut params = Vec::new();
+
+        if !self.eat(&SpecialCharacter(CloseParenthesis)) {
+            loop {
+                let start = self.peek_span();
+                let rest = self.eat(&SpecialCharacter(Ellipsis));
+                let name = self.expect_identifier("parameter name")?;
+                let span = start.to(&self.previous_span());
+
+                if rest {
+                    params.push(Parameter {
+                        name,
+                        ty: Some(Type::List),
+                        default: None,
+                        rest,
+                        span,
+                    });
+                    self.expect(
+                        SpecialCharacter(CloseParenthesis),
+                        "`)` after the rest parameter",
+                    )?;
+                    break;
+                }
+
+                let ty = self.parse_annotation()?;
+                let default = if self.eat(&SpecialCharacter(Equals)) {
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
+                if default.is_none()
+                    && params
+                        .last()
+                        .is_some_and(|param: &Parameter| param.default.is_some())
+                {
+                    return Err(ParseError {
+                        message: "a parameter without a default can't follow one with a default"
+                            .to_string(),
+                        span,
+                    });
+                }
+                params.push(Parameter {
+                    name,
+                    ty,
+                    default,
+                    rest,
+                    span,
+                });
+
+                if self.eat(&SpecialCharacter(CloseParenthesis)) {
+                    break;
+                }
+                self.expect(SpecialCharacter(Comma), "`,` or `)`")?;
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Parses an optional `: type` after a variable or parameter name.
+    fn parse_annotation(&mut self) -> Result<Option<Type>, ParseError> {
+        if self.eat(&SpecialCharacter(Colon)) {
+            self.parse_type().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        if let Some(Identifier(name)) = self.peek_kind()
+            && let Some(ty) = Type::from_name(name)
+        {
+            self.advance();
+            return Ok(ty);
+        }
+
+        Err(self
+            .error_expected("a type (`int`, `float`, `bool`, `char`, `string`, `list` or `map`)"))
+    }
+
+    fn parse_if(&mut self, indent: u8, start: &Span) -> Result<StatementKind, ParseError> {
+        self.advance();
+        let condition = self.parse_expression()?;
+        self.expect_end()?;
+        let body = self.parse_body(indent, &start.to(&self.previous_span()))?;
+        let mut branches = vec![(condition, body)];
+        let mut else_body = None;
+
+        while let Some(line) = self.lines.get(self.index) {
+            if line.indent != indent || line.has_errors || line.tokens[0].kind != Keyword(Else) {
+                break;
+            }
+
+            self.start_line();
+            let header = self.peek_span();
+            self.advance();
+
+            if self.eat(&Keyword(If)) {
+                let condition = self.parse_expression()?;
+                self.expect_end()?;
+                let body = self.parse_body(indent, &header.to(&self.previous_span()))?;
+                branches.push((condition, body));
+            } else {
+                self.expect_end()?;
+                else_body = Some(self.parse_body(indent, &header)?);
+                break;
+            }
+        }
+
+        Ok(StatementKind::If {
+            branches,
+            else_body,
+        })
+    }
+
+    /// Parses a `try` and the `catch` and `finally` after it, at least one
+    /// of which it needs.
+    fn parse_try(&mut self, indent: u8, start: &Span) -> Result<StatementKind, ParseError> {
+        self.advance();
+        self.expect_end()?;
+        let body = self.parse_body(indent, start)?;
+        let mut variable = None;
+        let mut catch_body = None;
+        let mut finally_body = None;
+
+        if self.at_branch(indent, Catch) {
+            self.start_line();
+            let header = self.peek_span();
+            self.advance();
+            if !self.at_end() {
+                variable = Some(self.expect_identifier("error variable name")?);
+            }
+            self.expect_end()?;
+            catch_body = Some(self.parse_body(indent, &header.to(&self.previous_span()))?);
+        }
+
+        if self.at_branch(indent, Finally) {
+            self.start_line();
+            let header = self.peek_span();
+            self.advance();
+            self.expect_end()?;
+            finally_body = Some(self.parse_body(indent, &header)?);
+        }
+
+        if catch_body.is_none() && finally_body.is_none() {
+            return Err(ParseError {
+                message: "expected a `catch` or `finally` after this `try`".to_string(),
+                span: start.clone(),
+            });
+        }
+
+        Ok(StatementKind::Try {
+            body,
+            variable,
+            catch_body,
+            finally_body,
+        })
+    }
+
+    /// Whether the next line continues a statement at `indent` with
+    /// `keyword`.
+    fn at_branch(&self, indent: u8, keyword: crate::types::Keyword) -> bool {
+        matches!(
+            self.lines.get(self.index),
+            Some(line) if line.indent == indent && !line.has_errors && line.tokens[0].kind == Keyword(keyword)
+        )
+    }
+
+    fn parse_loop(&mut self, indent: u8, start: &Span) -> Result<StatementKind, ParseError> {
+        self.advance();
+
+        if self.at_end() {
+            let body = self.parse_body(indent, start)?;
+            return Ok(StatementKind::Loop { body });
+        }
+
+        if let (Some(Identifier(variable)), Some(Keyword(In))) =
+            (self.peek_kind(), self.peek_kind_at(1))
+        {
+            let variable = variable.clone();
+            self.advance();
+            self.advance();
+            let iterable = self.parse_iterable()?;
+            self.expect_end()?;
+            let body = self.parse_body(indent, &start.to(&self.previous_span()))?;
+            return Ok(StatementKind::For {
+                variable,
+                value_variable: None,
+                iterable,
+                body,
+            });
+        }
+
+        if let (
+            Some(Identifier(variable)),
+            Some(SpecialCharacter(Comma)),
+            Some(Identifier(value_variable)),
+            Some(Keyword(In)),
+        ) = (
+            self.peek_kind(),
+            self.peek_kind_at(1),
+            self.peek_kind_at(2),
+            self.peek_kind_at(3),
+        ) {
+            let (variable, value_variable) = (variable.clone(), value_variable.clone());
+            self.position += 4;
+            let iterable = self.parse_iterable()?;
+            self.expect_end()?;
+            if let ExpressionKind::Range { .. } = iterable.kind {
+                return Err(ParseError {
+                    message: "a range has no keys or indices to loop over; use `loop i in` instead"
+                        .to_string(),
+                    span: iterable.span,
+                });
+            }
+            let body = self.parse_body(indent, &start.to(&self.previous_span()))?;
+            return Ok(StatementKind::For {
+                variable,
+                value_variable: Some(value_variable),
+                iterable,
+                body,
+            });
+        }
+
+        let condition = self.parse_expression()?;
+        self.expect_end()?;
+        let body = self.parse_body(indent, &start.to(&self.previous_span()))?;
+        Ok(StatementKind::While { condition, body })
+    }
+
+    /// Parses a `match` and its arms, indented one per line under it.
+    fn parse_match(&mut self, indent: u8, start: &Span) -> Result<StatementKind, ParseError> {
+        self.advance();
+        let value = self.parse_expression()?;
+        self.expect_end()?;
+
+        let arm_indent = match self.lines.get(self.index) {
+            Some(line) if line.indent > indent => line.indent,
+            _ => {
+                return Err(ParseError {
+                    message: "expected the match's arms, indented one per line".to_string(),
+                    span: start.to(&self.previous_span()),
+                });
+            }
+        };
+
+        // The statement's span is taken from the header, so the arms are
+        // parsed without losing its place.
+        let (tokens, position) = (self.tokens, self.position);
+        let mut arms = Vec::new();
+
+        while let Some(line) = self.lines.get(self.index)
+            && line.indent >= arm_indent
+        {
+            if line.has_errors {
+                self.index += 1;
+                self.skip_body(arm_indent);
+                continue;
+            }
+            if line.indent > arm_indent {
+                self.errors.push(ParseError {
+                    message: "unexpected indentation".to_string(),
+                    span: line.tokens[0].span.clone(),
+                });
+                self.parse_block(line.indent);
+                continue;
+            }
+
+            match self.parse_arm(arm_indent) {
+                Ok(arm) => arms.push(arm),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.skip_body(arm_indent);
+                }
+            }
+        }
+
+        self.tokens = tokens;
+        self.position = position;
+        Ok(StatementKind::Match { value, arms })
+    }
+
+    /// Parses an arm: a pattern and an optional `if` guard, then `=>` and
+    /// either a statement or the end of the line, with the arm's body
+    /// indented under it.
+    fn parse_arm(&mut self, indent: u8) -> Result<Arm, ParseError> {
+        self.start_line();
+        let start = self.peek_span();
+
+        // The guard is parsed without the `=>`, which would otherwise make
+        // a parenthesized guard look like a lambda.
+        let line = self.tokens;
+        let arrow = self.find_arrow().unwrap_or(line.len());
+        if arrow == 0 {
+            return Err(self.error_here("expected a pattern before `=>`"));
+        }
+        self.tokens = &line[..arrow];
+        let pattern = self.parse_pattern()?;
+        let guard = if self.eat(&Keyword(If)) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        if !self.at_end() {
+            return Err(self.error_expected(if guard.is_some() {
+                "`=>`"
+            } else {
+                "`if` or `=>`"
+            }));
+        }
+        let span = start.to(&self.previous_span());
+        self.tokens = line;
+        self.expect(SpecialCharacter(FatArrow), "`=>`")?;
+
+        let body = if self.at_end() {
+            self.parse_body(indent, &span)?
+        } else {
+            vec![self.parse_statement_here(indent)?]
+        };
+
+        Ok(Arm {
+            pattern,
+            guard,
+            body,
+            span,
+        })
+    }
+
+    /// The position of the first `=>` on the line that isn't inside
+    /// brackets.
+    fn find_arrow(&self) -> Option<usize> {
+        let mut depth = 0;
+        for (position, token) in self.tokens.iter().enumerate().skip(self.position) {
+            match token.kind {
+                SpecialCharacter(OpenParenthesis)
+                | SpecialCharacter(OpenBracket)
+                | SpecialCharacter(OpenBrace) => depth += 1,
+                SpecialCharacter(CloseParenthesis)
+                | SpecialCharacter(CloseBracket)
+                | SpecialCharacter(CloseBrace) => depth -= 1,
+                SpecialCharacter(FatArrow) if depth == 0 => return Some(position),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        match self.peek_kind() {
+            Some(Identifier(name)) if name == "_" => {
+                self.advance();
+                return Ok(Pattern::Wildcard);
+            }
+            Some(Identifier(name)) => {
+                let Some(ty) = Type::from_name(name) else {
+                    return Err(self.error_expected(PATTERN));
+                };
+                self.advance();
+                return Ok(Pattern::Type(ty));
+            }
+            Some(SpecialCharacter(OpenBracket)) => return self.parse_list_pattern(),
+            _ => {}
+        }
+
+        let start_span = self.peek_span();
+        let start = if self.at_range() {
+            None
+        } else {
+            let literal = self.parse_literal()?;
+            if !self.at_range() {
+                return Ok(Pattern::Literal(literal));
+            }
+            Some(literal)
+        };
+        self.advance();
+        let end = if self.at_end() || self.peek_kind() == Some(&Keyword(If)) {
+            None
+        } else {
+            Some(self.parse_literal()?)
+        };
+
+        // Number ranges match numbers and character ranges characters, so
+        // the bounds have to agree on which.
+        let kinds = start
+            .iter()
+            .chain(&end)
+            .map(|bound| match bound.kind {
+                ExpressionKind::Number(_) => Some(false),
+                ExpressionKind::Character(_) => Some(true),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let message = match kinds.as_slice() {
+            [Some(_)] => None,
+            [Some(start), Some(end)] if start == end => None,
+            [] => Some("a range pattern needs a start, an end or both"),
+            _ => Some("a range pattern's bounds must both be numbers or both be characters"),
+        };
+        if let Some(message) = message {
+            return Err(ParseError {
+                message: message.to_string(),
+                span: start_span.to(&self.previous_span()),
+            });
+        }
+
+        Ok(Pattern::Range { start, end })
+    }
+
+    /// Parses the literal of a pattern. Negative numbers are folded into the
+    /// literal, since patterns hold no other expressions.
+    fn parse_literal(&mut self) -> Result<Expression, ParseError> {
+        let span = self.peek_span();
+        let negative = self.eat(&SpecialCharacter(Minus));
+
+        let kind = match self.peek_kind() {
+            Some(Literal(Number(n))) if negative => ExpressionKind::Number(format!("-{n}")),
+            _ if negative => return Err(self.error_expected("a number")),
+            Some(Literal(Number(n))) => ExpressionKind::Number(n.clone()),
+            Some(Literal(Str(s))) => ExpressionKind::Str(s.clone()),
+            Some(Literal(Character(c))) => ExpressionKind::Character(c.clone()),
+            Some(Keyword(True)) => ExpressionKind::Bool(true),
+            Some(Keyword(False)) => ExpressionKind::Bool(false),
+            Some(Keyword(Null)) => ExpressionKind::Null,
+            _ => return Err(self.error_expected(PATTERN)),
+        };
+        self.advance();
+
+        Ok(Expression {
+            kind,
+            span: span.to(&self.previous_span()),
+        })
+    }
+
+    /// Parses `[first, second, rest...]`, whose rest must come last.
+    fn parse_list_pattern(&mut self) -> Result<Pattern, ParseError> {
+        self.advance();
+        let mut items = Vec::new();
+        let mut rest = None;
+
+        while !self.eat(&SpecialCharacter(CloseBracket)) {
+            let name = self.expect_identifier("a name")?;
+            if self.eat(&SpecialCharacter(Ellipsis)) {
+                rest = Some(name);
+                self.expect(
+                    SpecialCharacter(CloseBracket),
+                    "`]` after the rest of the list",
+                )?;
+                break;
+            }
+            items.push(name);
+
+            if self.eat(&SpecialCharacter(CloseBracket)) {
+                break;
+            }
+            self.expect(SpecialCharacter(Comma), "`,` or `]`")?;
+        }
+
+        Ok(Pattern::List { items, rest })
+    }
+
+    fn parse_iterable(&mut self) -> Result<Expression, ParseError> {
+        let start_span = self.peek_span();
+        let start = if self.at_range() {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+
+        if !self.at_range() {
+            return start.ok_or_else(|| self.error_here("expected an iterable expression"));
+        }
+
+        self.advance();
+        let end = if self.at_end() {
+            None
+        } else {
+            Some(Box::new(self.parse_expression()?))
+        };
+
+        Ok(Expression {
+            kind: ExpressionKind::Range {
+                start: start.map(Box::new),
+                end,
+            },
+            span: start_span.to(&self.previous_span()),
+        })
+    }
+
+    fn at_range(&self) -> bool {
+        self.peek_kind() == Some(&SpecialCharacter(DoubleDot))
+    }
+
+    fn parse_simple_statement(&mut self) -> Result<StatementKind, ParseError> {
+        let expression = self.parse_expression()?;
+
+        if self.at_end() {
+            return match expression.kind {
+                ExpressionKind::Call { .. } => Ok(StatementKind::Expression(expression)),
+                _ => Err(ParseError {
+                    message: "expected a statement, found an expression whose value is unused"
+                        .to_string(),
+                    span: expression.span,
+                }),
+            };
+        }
+
+        if self.eat(&SpecialCharacter(Equals)) {
+            let target = self.check_assignment_target(expression)?;
+            let value = self.parse_expression()?;
+            self.expect_end()?;
+            return Ok(StatementKind::Assign {
+                target,
+                operator: None,
+                value,
+            });
+        }
+
+        if let Some(operator) = self.peek_compound_operator() {
+            self.advance();
+            let target = self.check_assignment_target(expression)?;
+            let value = self.parse_expression()?;
+            self.expect_end()?;
+            return Ok(StatementKind::Assign {
+                target,
+                operator: Some(operator),
+                value,
+            });
+        }
+
+        if matches!(
+            self.peek_kind(),
+            Some(SpecialCharacter(DoublePlus)) | Some(SpecialCharacter(DoubleMinus))
+        ) {
+            let operator = self.parse_update_operator()?;
+            let target = self.check_assignment_target(expression)?;
+            self.expect_end()?;
+            return Ok(StatementKind::Update {
+                target,
+                operator,
+                prefix: false,
+            });
+        }
+
+        Err(self.error_here("unexpected token"))
+    }
+
+    fn peek_compound_operator(&self) -> Option<BinaryOperator> {
+        match self.peek_kind() {
+            Some(SpecialCharacter(PlusEquals)) => Some(BinaryOperator::Add),
+            Some(SpecialCharacter(MinusEquals)) => Some(BinaryOperator::Subtract),
+            Some(SpecialCharacter(AsteriskEquals)) => Some(BinaryOperator::Multiply),
+            Some(SpecialCharacter(SlashEquals)) => Some(BinaryOperator::Divide),
+            Some(SpecialCharacter(PercentEquals)) => Some(BinaryOperator::Modulo),
+            _ => None,
+        }
+    }
+
+    fn parse_update_operator(&mut self) -> Result<UpdateOperator, ParseError> {
+        let operator = match self.peek_kind() {
+            Some(SpecialCharacter(DoublePlus)) => UpdateOperator::Increment,
+            Some(SpecialCharacter(DoubleMinus)) => UpdateOperator::Decrement,
+            _ => return Err(self.error_here("expected `++` or `--`")),
+        };
+
+        self.advance();
+        Ok(operator)
+    }
+
+    fn parse_assignment_target(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.parse_postfix()?;
+        self.check_assignment_target(expression)
+    }
+
+    fn check_assignment_target(&self, expression: Expression) -> Result<Expression, ParseError> {
+        match expression.kind {
+            ExpressionKind::Identifier(_)
+            | ExpressionKind::ModuleMember { .. }
+            | ExpressionKind::Index { .. }
+            | ExpressionKind::Member { .. } => Ok(expression),
+            _ => Err(ParseError {
+                message: "invalid assignment target".to_string(),
+                span: expression.span,
+            }),
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        self.parse_expression_with(0)
+    }
+
+    /// Pratt parser over Lyra's operator table. Binary operators bind
+    /// tighter the higher their power, `not` sits between `and` and the
+    /// comparisons so `not a == b` negates the comparison, and comparisons
+    /// do not chain.
+    fn parse_expression_with(&mut self, min_power: u8) -> Result<Expression, ParseError> {
+        let mut left = self.parse_prefix()?;
+        let mut last_comparison = false;
+
+        while let Some(operator) = self.peek_binary_operator()? {
+            let (left_power, right_power) = binding_power(operator);
+            if left_power < min_power {
+                break;
+            }
+
+            if is_comparison(operator) && last_comparison {
+                return Err(self.error_here(
+                    "comparison operators cannot be chained; combine comparisons with `and`",
+                ));
+            }
+
+            self.advance();
+
+            if self.at_end() {
+                return Err(self.error_here(&format!(
+                    "expected an expression after `{}`",
+                    operator.symbol()
+                )));
+            }
+
+            let right = self.parse_expression_with(right_power)?;
+            left = binary(operator, left, right);
+            last_comparison = is_comparison(operator);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression, ParseError> {
+        let start = self.peek_span();
+        let (operator, power) = match self.peek_kind() {
+            Some(Keyword(Not)) => (UnaryOperator::Not, NOT_POWER),
+            Some(SpecialCharacter(Minus)) => (UnaryOperator::Negate, NEGATE_POWER),
+            Some(SpecialCharacter(ExclamationMark)) => {
+                return Err(self.error_here("use `not` for logical negation"));
+            }
+            _ => return self.parse_postfix(),
+        };
+
+        self.advance();
+        let operand = self.parse_expression_with(power)?;
+
+        Ok(Expression {
+            span: start.to(&operand.span),
+            kind: ExpressionKind::Unary {
+                operator,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    fn peek_binary_operator(&self) -> Result<Option<BinaryOperator>, ParseError> {
+        let operator = match self.peek_kind() {
+            Some(Keyword(Or)) => BinaryOperator::Or,
+            Some(Keyword(And)) => BinaryOperator::And,
+            Some(Keyword(In)) => BinaryOperator::In,
+            Some(SpecialCharacter(DoubleEquals)) => BinaryOperator::Equal,
+            Some(SpecialCharacter(NotEquals)) => BinaryOperator::NotEqual,
+            Some(SpecialCharacter(SmallerThan)) => BinaryOperator::Less,
+            Some(SpecialCharacter(LargerThan)) => BinaryOperator::Greater,
+            Some(SpecialCharacter(SmallerThanOrEquals)) => BinaryOperator::LessEqual,
+            Some(SpecialCharacter(LargerThanOrEquals)) => BinaryOperator::GreaterEqual,
+            Some(SpecialCharacter(Plus)) => BinaryOperator::Add,
+            Some(SpecialCharacter(Minus)) => BinaryOperator::Subtract,
+            Some(SpecialCharacter(Asterisk)) => BinaryOperator::Multiply,
+            Some(SpecialCharacter(Slash)) => BinaryOperator::Divide,
+            Some(SpecialCharacter(Percent)) => BinaryOperator::Modulo,
+            Some(SpecialCharacter(Equals)) | Some(SpecialCharacter(ExclamationMark))
+                if self.peek_kind_at(1) == Some(&SpecialCharacter(Equals)) =>
+            {
+                return Err(self.error_here("`==` and `!=` must be written without spaces"));
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(operator))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.parse_primary()?;
+
+        loop {
+            let kind = match (self.peek_kind(), self.peek_kind_at(1)) {
+                (Some(SpecialCharacter(OpenParenthesis)), _) => {
+                    self.advance();
+                    let (arguments, names) = self.parse_arguments()?;
+                    ExpressionKind::Call {
+                        callee: Box::new(expression.clone()),
+                        arguments,
+                        names,
+                    }
+                }
+                (Some(SpecialCharacter(OpenBracket)), _) => {
+                    self.advance();
+                    let index = self.parse_expression()?;
+                    self.expect(SpecialCharacter(CloseBracket), "`]`")?;
+                    ExpressionKind::Index {
+                        object: Box::new(expression.clone()),
+                        index: Box::new(index),
+                    }
+                }
+                (Some(SpecialCharacter(Dot)), Some(Identifier(name))) => {
+                    let name = name.clone();
+                    self.advance();
+                    self.advance();
+                    ExpressionKind::Member {
+                        object: Box::new(expression.clone()),
+                        name,
+                    }
+                }
+                _ => break,
+            };
+
+            expression = Expression {
+                span: expression.span.to(&self.previous_span()),
+                kind,
+            };
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        if self.at_lambda() {
+            return self.parse_lambda();
+        }
+
+        let span = self.peek_span();
+        let token = match self.peek_kind() {
+            Some(token) => token.clone(),
+            None => return Err(self.error_here("expected an expression")),
+        };
+        self.advance();
+
+        let kind = match token {
+            Literal(Number(n)) => ExpressionKind::Number(n),
+            Literal(Str(s)) => ExpressionKind::Str(s),
+            Literal(Character(c)) => ExpressionKind::Character(c),
+            Keyword(True) => ExpressionKind::Bool(true),
+            Keyword(False) => ExpressionKind::Bool(false),
+            Keyword(Null) => ExpressionKind::Null,
+            Identifier(name) => {
+                if self.eat(&SpecialCharacter(DoubleColon)) {
+                    let member = self.expect_identifier("module member name")?;
+                    ExpressionKind::ModuleMember {
+                        module: name,
+                        name: member,
+                    }
+                } else {
+                    ExpressionKind::Identifier(name)
+                }
+            }
+            SpecialCharacter(OpenParenthesis) => {
+                let expression = self.parse_expression()?;
+                self.expect(SpecialCharacter(CloseParenthesis), "`)`")?;
+                return Ok(Expression {
+                    span: span.to(&self.previous_span()),
+                    kind: expression.kind,
+                });
+            }
+            SpecialCharacter(OpenBrace) => self.parse_braces()?,
+            _ => {
+                return Err(ParseError {
+                    message: format!("expected an expression, found {}", describe(&token)),
+                    span,
+                });
+            }
+        };
+
+        Ok(Expression {
+            span: span.to(&self.previous_span()),
+            kind,
+        })
+    }
+
+    /// Whether the parenthesis ahead opens the parameters of a lambda, which
+    /// is only known from the `=>` after its closing parenthesis.
+    fn at_lambda(&self) -> bool {
+        if self.peek_kind() != Some(&SpecialCharacter(OpenParenthesis)) {
+            return false;
+        }
+
+        let mut depth = 0;
+        for offset in 0.. {
+            match self.peek_kind_at(offset) {
+                Some(SpecialCharacter(OpenParenthesis)) => depth += 1,
+                Some(SpecialCharacter(CloseParenthesis)) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.peek_kind_at(offset + 1) == Some(&SpecialCharacter(FatArrow));
+                    }
+                }
+                None => return false,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    /// Parses `(params) => value`, or a `(params) =>` ending its source line
+    /// with the lambda's body indented under the statement. Inside brackets,
+    /// the lexer has put the body's lines after the statement's line, and
+    /// the statement carries on after the `=>`.
+    fn parse_lambda(&mut self) -> Result<Expression, ParseError> {
+        let start = self.peek_span();
+        let params = self.parse_params()?;
+        self.advance();
+        let header = start.to(&self.previous_span());
+
+        // Inside brackets, the body's lines are the ones between the `=>`
+        // and the rest of the statement.
+        let arrow = self.previous_span().line;
+        let rest = (!self.at_end()).then(|| self.peek_span().line);
+        let body_lines = rest.map_or(0, |rest| {
+            self.lines[self.index..]
+                .iter()
+                .take_while(|line| {
+                    line.tokens
+                        .first()
+                        .is_none_or(|token| token.span.line > arrow && token.span.line < rest)
+                })
+                .count()
+        });
+
+        if rest.is_some() && body_lines == 0 {
+            let value = self.parse_expression()?;
+            return Ok(Expression {
+                span: start.to(&value.span),
+                kind: ExpressionKind::Lambda {
+                    params,
+                    body: vec![Statement {
+                        span: value.span.clone(),
+                        kind: StatementKind::Return(Some(value)),
+                    }],
+                },
+            });
+        }
+
+        // The body spans the lines after this one, so the rest of the
+        // statement is parsed from where this line left off.
+        let (lines, tokens, position, indent) =
+            (self.lines, self.tokens, self.position, self.indent);
+        if rest.is_some() {
+            self.lines = &lines[..self.index + body_lines];
+        }
+        let body = self.parse_body(indent, &header);
+        self.lines = lines;
+        self.tokens = tokens;
+        self.position = position;
+        self.indent = indent;
+
+        Ok(Expression {
+            span: header,
+            kind: ExpressionKind::Lambda {
+                params,
+                body: body?,
+            },
+        })
+    }
+
+    /// Parses what follows a `{`: a list, or a map if its first item is
+    /// followed by `:`. `{:}` is the empty map, since `{}` is the empty
+    /// list.
+    fn parse_braces(&mut self) -> Result<ExpressionKind, ParseError> {
+        if self.eat(&SpecialCharacter(Colon)) {
+            self.expect(SpecialCharacter(CloseBrace), "`}`")?;
+            return Ok(ExpressionKind::Map(Vec::new()));
+        }
+        if self.eat(&SpecialCharacter(CloseBrace)) {
+            return Ok(ExpressionKind::List(Vec::new()));
+        }
+
+        let first = self.parse_expression()?;
+        if !self.eat(&SpecialCharacter(Colon)) {
+            let mut items = vec![first];
+            if !self.eat(&SpecialCharacter(CloseBrace)) {
+                self.expect(SpecialCharacter(Comma), "`,` or `}`")?;
+                items.extend(self.parse_list(CloseBrace, "`,` or `}`")?);
+            }
+            return Ok(ExpressionKind::List(items));
+        }
+
+        let mut entries = vec![(first, self.parse_expression()?)];
+        // A trailing comma is allowed here too.
+        while !self.eat(&SpecialCharacter(CloseBrace)) {
+            self.expect(SpecialCharacter(Comma), "`,` or `}`")?;
+            if self.eat(&SpecialCharacter(CloseBrace)) {
+                break;
+            }
+            let key = self.parse_expression()?;
+            self.expect(SpecialCharacter(Colon), "`:`")?;
+            entries.push((key, self.parse_expression()?));
+        }
+
+        Ok(ExpressionKind::Map(entries))
+    }
+
+    /// Parses a call's arguments after its `(`, returning them with the
+    /// names of those passed as `name: value`, which come last.
+    fn parse_arguments(&mut self) -> Result<(Vec<Expression>, Vec<String>), ParseError> {
+        let close = SpecialCharacter(CloseParenthesis);
+        let mut arguments = Vec::new();
+        let mut names = Vec::new();
+
+        while !self.eat(&close) {
+            if let Some(Identifier(name)) = self.peek_kind()
+                && self.peek_kind_at(1) == Some(&SpecialCharacter(Colon))
+            {
+                names.push(name.clone());
+                self.advance();
+                self.advance();
+            } else if !names.is_empty() {
+                return Err(self.error_here(
+                    "arguments passed by position must come before those passed by name",
+                ));
+            }
+            arguments.push(self.parse_expression()?);
+
+            if self.eat(&close) {
+                break;
+            }
+            self.expect(SpecialCharacter(Comma), "`,` or `)`")?;
+        }
+
+        Ok((arguments, names))
+    }
+
+    fn parse_list(
+        &mut self,
+        close: crate::types::SpecialCharacter,
+        expected: &str,
+    ) -> Result<Vec<Expression>, ParseError> {
+        let close = SpecialCharacter(close);
+        let mut items = Vec::new();
+
+        // A trailing comma is allowed so lists can be split one item per line.
+        while !self.eat(&close) {
+            items.push(self.parse_expression()?);
+
+            if self.eat(&close) {
+                break;
+            }
+            self.expect(SpecialCharacter(Comma), expected)?;
+        }
+
+        Ok(items)
+    }
+
+    fn start_line(&mut self) {
+        self.tokens = &self.lines[self.index].tokens;
+        self.position = 0;
+        self.index += 1;
+    }
+
+    fn peek_kind(&self) -> Option<&TokenKind> {
+        self.peek_kind_at(0)
+    }
+
+    fn peek_kind_at(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens
+            .get(self.position + offset)
+            .map(|token| &token.kind)
+    }
+
+    fn peek_span(&self) -> Span {
+        match self.tokens.get(self.position) {
+            Some(token) => token.span.clone(),
+            None => match self.tokens.last() {
+                Some(last) => Span {
+                    column: last.span.column + (last.span.end - last.span.start),
+                    start: last.span.end,
+                    ..last.span.clone()
+                },
+                // Part of a line cut off before its first token has nothing
+                // to point past, so the whole line is pointed at.
+                None => {
+                    let line = &self.lines[self.index - 1].tokens;
+                    line[0].span.to(&line[line.len() - 1].span)
+                }
+            },
+        }
+    }
+
+    fn previous_span(&self) -> Span {
+        self.tokens[self.position.max(1) - 1].span.clone()
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek_kind() == Some(kind) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<(), ParseError> {
+        if self.eat(&kind) {
+            Ok(())
+        } else {
+            Err(self.error_expected(expected))
+        }
+    }
+
+    fn expect_identifier(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.peek_kind() {
+            Some(Identifier(name)) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.error_expected(expected)),
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), ParseError> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.error_expected("end of line"))
+        }
+    }
+
+    fn error_expected(&self, expected: &str) -> ParseError {
+        let found = match self.peek_kind() {
+            Some(token) => describe(token),
+            None => "end of line".to_string(),
+        };
+
+        self.error_here(&format!("expected {expected}, found {found}"))
+    }
+
+    fn error_here(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            span: self.peek_span(),
+        }
+    }
+}
+
+const PATTERN: &str = "a pattern (a literal, range, type, list or `_`)";
+
+const NOT_POWER: u8 = 5;
+const NEGATE_POWER: u8 = 13;
+
+/// Left and right binding power of each binary operator, from `or` (loosest)
+/// to `*`, `/` and `%` (tightest). Calls, indexing and member access bind
+/// tighter than all of them and are handled by `parse_postfix`.
+fn binding_power(operator: BinaryOperator) -> (u8, u8) {
+    match operator {
+        BinaryOperator::Or => (1, 2),
+        BinaryOperator::And => (3, 4),
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::Less
+        | BinaryOperator::Greater
+        | BinaryOperator::LessEqual
+        | BinaryOperator::GreaterEqual
+        | BinaryOperator::In => (7, 8),
+        BinaryOperator::Add | BinaryOperator::Subtract => (9, 10),
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => (11, 12),
+    }
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    binding_power(operator) == (7, 8)
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    Expression {
+        span: left.span.to(&right.span),
+        kind: ExpressionKind::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+    }
+}
+
+fn describe(token: &TokenKind) -> String {
+    match token {
+        Identifier(name) => format!("`{name}`"),
+        Keyword(keyword) => format!("keyword `{}`", format!("{keyword:?}").to_lowercase()),
+        SpecialCharacter(character) => format!("`{}`", special_character(character)),
+        Literal(Str(_)) => "string literal".to_string(),
+        Literal(Character(_)) => "character literal".to_string(),
+        Literal(Number(n)) => format!("number `{n}`"),
+    }
+}
+
+fn special_character(character: &crate::types::SpecialCharacter) -> &'static str {
+    match character {
+        Equals => "=",
+        ExclamationMark => "!",
+        Dot => ".",
+        Colon => ":",
+        Comma => ",",
+        Plus => "+",
+        Minus => "-",
+        Asterisk => "*",
+        Slash => "/",
+        Percent => "%",
+        LargerThan => ">",
+        SmallerThan => "<",
+        OpenParenthesis => "(",
+        CloseParenthesis => ")",
+        OpenBracket => "[",
+        CloseBracket => "]",
+        OpenBrace => "{",
+        CloseBrace => "}",
+        DoubleEquals => "==",
+        NotEquals => "!=",
+        SmallerThanOrEquals => "<=",
+        LargerThanOrEquals => ">=",
+        PlusEquals => "+=",
+        MinusEquals => "-=",
+        AsteriskEquals => "*=",
+        SlashEquals => "/=",
+        PercentEquals => "%=",
+        DoubleColon => "::",
+        DoubleDot => "..",
+        Ellipsis => "...",
+        DoublePlus => "++",
+        DoubleMinus => "--",
+        Arrow => "->",
+        FatArrow => "=>",
+        Backslash => "\\",
+    }
+}
@@ -0,0 +1,566 @@
+use crate::ast::*;
+use crate::infer::{Position, position};
+use crate::symbols::{Binding, Slot, Symbols, bind_arguments};
+use crate::types::{Diagnostic, Span};
+use std::collections::{HashMap, HashSet};
+
+/// How the arguments of each call to a known function or struct, and of
+/// each method call that names its arguments, fill the parameters.
+pub type Calls = HashMap<(Position, usize), Vec<Slot>>;
+
+/// The key of a call in `Calls`: where it starts and ends, since a call
+/// and the call made on its result start at the same place.
+pub fn call_key(span: &Span) -> (Position, usize) {
+    (position(span), span.end)
+}
+
+/// Checks that every name in the program refers to something declared.
+/// Scopes follow the C++ the generator emits: a module's variables,
+/// constants and functions are visible everywhere in it, as are the
+/// functions and structs of `main.ly`, while other names in `main.ly` and in
+/// bodies are visible from their declaration to the end of the enclosing
+/// block. Assignments are checked against what the name is bound to, so
+/// constants, loop variables and functions can't be changed, and neither
+/// can the locals a lambda captures, since it only holds copies of them.
+/// Calls to functions and methods are checked against their
+/// parameters, and how their arguments fit is returned for each module.
+/// Imports no name refers to are reported as unused.
+pub fn resolve(modules: &[Module], symbols: &Symbols) -> (Vec<Diagnostic>, Vec<Calls>) {
+    let mut diagnostics = Vec::new();
+    let mut calls = Vec::new();
+
+    for module in modules {
+        let mut resolver = Resolver {
+            module,
+            symbols,
+            scopes: Vec::new(),
+            lambdas: Vec::new(),
+            used_imports: HashSet::new(),
+            calls: Calls::new(),
+            diagnostics: Vec::new(),
+        };
+        resolver.resolve_module();
+        diagnostics.extend(resolver.diagnostics);
+        calls.push(resolver.calls);
+    }
+
+    (diagnostics, calls)
+}
+
+struct Resolver<'a> {
+    module: &'a Module,
+    symbols: &'a Symbols<'a>,
+    scopes: Vec<HashMap<String, Binding>>,
+    /// How many scopes were open where each lambda being resolved starts.
+    lambdas: Vec<usize>,
+    used_imports: HashSet<String>,
+    calls: Calls,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver<'_> {
+    fn resolve_module(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.resolve_definitions();
+
+        for import in &self.module.imports {
+            // A missing module is already an error of its own.
+            if !self.used_imports.contains(&import.module)
+                && self.symbols.exports.contains_key(import.module.as_str())
+            {
+                self.diagnostics.push(Diagnostic::warning(
+                    format!("unused import `{}`", import.module),
+                    &import.span,
+                ));
+            }
+        }
+    }
+
+    /// Resolves the top level of a module, whose definitions can be used
+    /// before they appear. The variables and constants of `main.ly` are
+    /// locals of `main()` instead, in a scope of their own.
+    fn resolve_definitions(&mut self) {
+        let main = self.module.name == "main";
+        let mut methods = HashSet::new();
+
+        for statement in &self.module.statements {
+            match &statement.kind {
+                StatementKind::Let { name, .. } if !main => {
+                    self.declare(name, Binding::Variable, &statement.span)
+                }
+                StatementKind::Const { name, .. } if !main => {
+                    self.declare(name, Binding::Constant, &statement.span)
+                }
+                StatementKind::Function { name, .. } => {
+                    self.declare(name, Binding::Function, &statement.span)
+                }
+                StatementKind::Struct { name, .. } => {
+                    self.declare(name, Binding::Struct, &statement.span)
+                }
+                StatementKind::Method { name, receiver, .. } => {
+                    let description = match receiver {
+                        Some(receiver) => format!("`{receiver}.{name}`"),
+                        None => format!("`{name}`"),
+                    };
+                    if !methods.insert((receiver, name)) {
+                        self.error(
+                            format!("method {description} is already defined in this module"),
+                            &statement.span,
+                        );
+                    }
+                    if let Some(receiver) = receiver
+                        && !self.module.statements.iter().any(|statement| {
+                            matches!(&statement.kind, StatementKind::Struct { name, .. } if name == receiver)
+                        })
+                    {
+                        self.error(
+                            format!("method {description} names `{receiver}`, which is not a struct defined in this module"),
+                            &statement.span,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if main {
+            self.scopes.push(HashMap::new());
+        }
+
+        for statement in &self.module.statements {
+            match &statement.kind {
+                StatementKind::Let { value, .. } if !main => self.resolve_optional(value.as_ref()),
+                StatementKind::Const { value, .. } if !main => self.resolve_expression(value),
+                StatementKind::Function { params, body, .. }
+                | StatementKind::Method { params, body, .. } => {
+                    // Functions are generated outside of `main()`, so the
+                    // locals of `main.ly` are hidden from them.
+                    let locals = self.scopes.split_off(1);
+                    self.scopes.push(HashMap::new());
+                    self.declare_params(params);
+                    self.resolve_statements(body);
+                    self.scopes.pop();
+                    self.scopes.extend(locals);
+                }
+                StatementKind::Struct { fields, .. } => self.check_fields(fields),
+                _ => self.resolve_statement(statement),
+            }
+        }
+    }
+
+    fn resolve_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.resolve_statements(block);
+        self.scopes.pop();
+    }
+
+    fn resolve_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let { name, value, .. } => {
+                self.resolve_optional(value.as_ref());
+                self.declare(name, Binding::Variable, &statement.span);
+            }
+            StatementKind::Const { name, value, .. } => {
+                self.resolve_expression(value);
+                self.declare(name, Binding::Constant, &statement.span);
+            }
+            StatementKind::Assign { target, value, .. } => {
+                self.resolve_expression(target);
+                self.resolve_expression(value);
+                self.check_mutable(target, "assign to");
+            }
+            StatementKind::Update {
+                target, operator, ..
+            } => {
+                self.resolve_expression(target);
+                self.check_mutable(
+                    target,
+                    match operator {
+                        UpdateOperator::Increment => "increment",
+                        UpdateOperator::Decrement => "decrement",
+                    },
+                );
+            }
+            StatementKind::Expression(expression) => self.resolve_expression(expression),
+            StatementKind::If {
+                branches,
+                else_body,
+            } => {
+                for (condition, body) in branches {
+                    self.resolve_expression(condition);
+                    self.resolve_block(body);
+                }
+                if let Some(body) = else_body {
+                    self.resolve_block(body);
+                }
+            }
+            StatementKind::Loop { body } => self.resolve_block(body),
+            StatementKind::While { condition, body } => {
+                self.resolve_expression(condition);
+                self.resolve_block(body);
+            }
+            StatementKind::For {
+                variable,
+                value_variable,
+                iterable,
+                body,
+            } => {
+                self.resolve_expression(iterable);
+                // The loop variables share a scope with the body, as in a
+                // C++ range-for, so the body can't redeclare them.
+                self.scopes.push(HashMap::new());
+                self.declare(variable, Binding::LoopVariable, &statement.span);
+                if let Some(value_variable) = value_variable {
+                    self.declare(value_variable, Binding::LoopVariable, &statement.span);
+                }
+                self.resolve_statements(body);
+                self.scopes.pop();
+            }
+            StatementKind::Match { value, arms } => {
+                self.resolve_expression(value);
+                for arm in arms {
+                    // An arm's names are bound for its guard and its body.
+                    self.scopes.push(HashMap::new());
+                    for name in arm.pattern.bindings() {
+                        self.declare(name, Binding::Variable, &arm.span);
+                    }
+                    self.resolve_optional(arm.guard.as_ref());
+                    self.resolve_statements(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            StatementKind::Try {
+                body,
+                variable,
+                catch_body,
+                finally_body,
+            } => {
+                self.resolve_block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = variable {
+                        self.declare(variable, Binding::Variable, &statement.span);
+                    }
+                    self.resolve_statements(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.resolve_block(finally_body);
+                }
+            }
+            StatementKind::Throw(value) | StatementKind::Yield(value) => {
+                self.resolve_expression(value)
+            }
+            StatementKind::Break | StatementKind::Continue => {}
+            StatementKind::Return(value) => self.resolve_optional(value.as_ref()),
+            StatementKind::Function { .. }
+            | StatementKind::Method { .. }
+            | StatementKind::Struct { .. } => {
+                unreachable!("Definitions only appear at the top level of a file")
+            }
+        }
+    }
+
+    /// Declares parameters in order, so a default can use the parameters
+    /// before it.
+    fn declare_params(&mut self, params: &[Parameter]) {
+        for param in params {
+            self.resolve_optional(param.default.as_ref());
+            self.declare(&param.name, Binding::Variable, &param.span);
+        }
+    }
+
+    fn check_fields(&mut self, fields: &[Parameter]) {
+        let mut names = HashSet::new();
+
+        for field in fields {
+            if !names.insert(&field.name) {
+                self.error(
+                    format!("field `{}` is already defined in this struct", field.name),
+                    &field.span,
+                );
+            }
+        }
+    }
+
+    fn resolve_optional(&mut self, expression: Option<&Expression>) {
+        if let Some(expression) = expression {
+            self.resolve_expression(expression);
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Number(_)
+            | ExpressionKind::Str(_)
+            | ExpressionKind::Character(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Null => {}
+            ExpressionKind::Identifier(name) => {
+                if self.lookup(name).is_none() {
+                    self.error(
+                        format!("cannot find `{name}` in this scope"),
+                        &expression.span,
+                    );
+                }
+            }
+            ExpressionKind::ModuleMember { module, name } => {
+                self.used_imports.insert(module.clone());
+                if !self.is_imported(module) {
+                    self.error(
+                        format!("module `{module}` is not imported"),
+                        &expression.span,
+                    );
+                } else if self.lookup_member(module, name).is_none()
+                    // Members of a module missing from the project can't be
+                    // checked.
+                    && self.symbols.exports.contains_key(module.as_str())
+                {
+                    self.error(
+                        format!("module `{module}` has no member `{name}`"),
+                        &expression.span,
+                    );
+                }
+            }
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.resolve_expression(item);
+                }
+            }
+            ExpressionKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            ExpressionKind::Range { start, end } => {
+                self.resolve_optional(start.as_deref());
+                self.resolve_optional(end.as_deref());
+            }
+            ExpressionKind::Unary { operand, .. } => self.resolve_expression(operand),
+            ExpressionKind::Binary { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            ExpressionKind::Call {
+                callee,
+                arguments,
+                names,
+            } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+                self.check_call(callee, arguments, names, &expression.span);
+            }
+            ExpressionKind::Index { object, index } => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            ExpressionKind::Member { object, .. } => self.resolve_expression(object),
+            ExpressionKind::Lambda { params, body } => {
+                self.lambdas.push(self.scopes.len());
+                self.scopes.push(HashMap::new());
+                self.declare_params(params);
+                self.resolve_statements(body);
+                self.scopes.pop();
+                self.lambdas.pop();
+            }
+        }
+    }
+
+    /// Reports an assignment to a name that can't change, or to an element
+    /// or field of one. The target has already been resolved, so names that
+    /// don't exist are skipped here.
+    fn check_mutable(&mut self, target: &Expression, action: &str) {
+        let part = match &target.kind {
+            ExpressionKind::Index { .. } => "an element of ",
+            ExpressionKind::Member { .. } => "a field of ",
+            _ => "",
+        };
+        let mut root = target;
+        while let ExpressionKind::Index { object, .. } | ExpressionKind::Member { object, .. } =
+            &root.kind
+        {
+            root = object;
+        }
+
+        let (name, binding) = match &root.kind {
+            ExpressionKind::Identifier(name) => (name.clone(), self.lookup(name)),
+            ExpressionKind::ModuleMember { module, name } => (
+                format!("{module}::{name}"),
+                self.lookup_member(module, name),
+            ),
+            _ => return,
+        };
+        if let ExpressionKind::Identifier(name) = &root.kind
+            && self.is_captured(name)
+        {
+            self.error(
+                format!("cannot {action} {part}`{name}`, which the lambda captured by value"),
+                &target.span,
+            );
+            return;
+        }
+        let Some(kind) = binding.and_then(|binding| binding.describe_immutable()) else {
+            return;
+        };
+
+        self.error(
+            format!("cannot {action} {part}{kind} `{name}`"),
+            &target.span,
+        );
+    }
+
+    /// Matches the arguments of a call to a known function, method or struct
+    /// to its parameters, reporting any that don't fit. How they fit is
+    /// recorded for the generator, which passes them in order. A method is
+    /// called with its receiver as the first parameter and dispatched at
+    /// runtime, so the call only has to fit one of the methods sharing its
+    /// name, and only needs recording when it names its arguments.
+    fn check_call(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+        names: &[String],
+        span: &Span,
+    ) {
+        let functions = &self.symbols.functions;
+        let (name, signatures, skip) = match &callee.kind {
+            ExpressionKind::Identifier(name)
+                if matches!(self.lookup(name), Some(Binding::Function | Binding::Struct)) =>
+            {
+                let function = functions.get(&(self.module.name.as_str(), name.as_str()));
+                (format!("`{name}`"), function.into_iter().collect(), 0)
+            }
+            ExpressionKind::ModuleMember { module, name }
+                if matches!(
+                    self.lookup_member(module, name),
+                    Some(Binding::Function | Binding::Struct)
+                ) =>
+            {
+                let function = functions.get(&(module.as_str(), name.as_str()));
+                (
+                    format!("`{module}::{name}`"),
+                    function.into_iter().collect(),
+                    0,
+                )
+            }
+            ExpressionKind::Member { name, .. } => (
+                format!("method `{name}`"),
+                self.symbols
+                    .methods
+                    .get(name.as_str())
+                    .map(|methods| methods.iter().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+                1,
+            ),
+            _ => (String::new(), Vec::new(), 0),
+        };
+
+        let Some(first) = signatures.first() else {
+            if !names.is_empty() {
+                self.error(
+                    "only functions, methods and structs take arguments by name".to_string(),
+                    span,
+                );
+            }
+            return;
+        };
+
+        if !names.is_empty()
+            && signatures.iter().any(|signature| {
+                !signature
+                    .params_from(skip)
+                    .iter()
+                    .map(|param| &param.name)
+                    .eq(first.params_from(skip).iter().map(|param| &param.name))
+            })
+        {
+            self.error(
+                format!("{name} has different parameters in different modules, so it can't take arguments by name"),
+                span,
+            );
+            return;
+        }
+
+        let mut results = signatures.iter().map(|signature| {
+            bind_arguments(&name, signature.params_from(skip), arguments.len(), names)
+        });
+        let slots = match results.find(Result::is_ok) {
+            Some(found) => found,
+            None => bind_arguments(&name, first.params_from(skip), arguments.len(), names),
+        };
+        match slots {
+            Ok(slots) if skip == 0 || !names.is_empty() => {
+                self.calls.insert(call_key(span), slots);
+            }
+            Ok(_) => {}
+            Err(message) => self.diagnostics.push(
+                Diagnostic::error(message, span)
+                    .with_note(format!("{name} is defined here"), first.span),
+            ),
+        }
+    }
+
+    fn is_imported(&self, module: &str) -> bool {
+        module == self.module.name
+            || self
+                .module
+                .imports
+                .iter()
+                .any(|import| import.module == module)
+    }
+
+    /// Whether a name is a local from outside the innermost lambda, which the
+    /// lambda holds a copy of. Variables at the top level of a module other
+    /// than `main.ly` are globals, which are used directly, while those of
+    /// `main.ly` are in the scope after them.
+    fn is_captured(&self, name: &str) -> bool {
+        let Some(&lambda) = self.lambdas.last() else {
+            return false;
+        };
+
+        self.scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name))
+            .is_some_and(|depth| depth < lambda && depth > 0)
+    }
+
+    fn lookup_member(&self, module: &str, name: &str) -> Option<Binding> {
+        self.symbols.exports.get(module)?.get(name).copied()
+    }
+
+    fn declare(&mut self, name: &str, binding: Binding, span: &Span) {
+        if crate::generator::RESERVED.contains(&name) {
+            self.error(format!("`{name}` is reserved for the compiler"), span);
+        }
+        let scope = self.scopes.last_mut().expect("A scope is always open");
+
+        if scope.insert(name.to_string(), binding).is_some() {
+            self.error(format!("`{name}` is already defined in this scope"), span);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .or_else(|| {
+                crate::generator::BUILTINS
+                    .contains(&name)
+                    .then_some(Binding::Builtin)
+            })
+    }
+
+    fn error(&mut self, message: String, span: &Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+}
@@ -2,15 +2,32 @@
 
 #include <cmath>
 #include <cstring>
+#include <exception>
+#include <memory>
 #include <sstream>
+#include <utility>
 
-std::unordered_map<std::string, std::function<Value(const std::vector<Value>&)>>
-    Value::global_methods_;
+// Methods are registered from static initializers in other translation units,
+// so the registry must be constructed on first use.
+std::unordered_map<std::string, std::function<Value(const std::vector<Value>&)>>&
+Value::global_methods() {
+    static std::unordered_map<std::string,
+                              std::function<Value(const std::vector<Value>&)>>
+        methods;
+    return methods;
+}
 
 Value::Value() : type_(Type::Null), value_(0.0) {}
 
 Value::Value(const Value& other)
-    : type_(other.type_), value_(other.value_), list_(other.list_) {}
+    : type_(other.type_),
+      value_(other.value_),
+      list_(other.list_),
+      values_(other.values_),
+      index_(other.index_),
+      function_(other.function_),
+      fields_(other.fields_),
+      name_(other.name_) {}
 
 Value::Value(std::nullptr_t) : type_(Type::Null) {}
 
@@ -35,6 +52,32 @@ Value::Value(const std::vector<Value>& value)
 Value::Value(std::function<Value(const std::vector<Value>&)> func)
     : type_(Type::Function), value_(0.0), function_(func) {}
 
+Value::Value(const std::vector<std::pair<Value, Value>>& entries)
+    : type_(Type::Map) {
+    for (const auto& [key, value] : entries) entry(key) = value;
+}
+
+// Keys are hashed by what they compare equal to, so `1`, `1.0` and `true`
+// are the same key, as they are with `==`.
+std::string Value::get_key(const Value& key) {
+    if (key.is_value()) {
+        double number = key.value_ == 0.0 ? 0.0 : key.value_;
+        uint64_t bits;
+        std::memcpy(&bits, &number, sizeof bits);
+        return "v" + std::to_string(bits);
+    }
+    if (key.type_ == Type::String) return "s" + key.to_string();
+    if (key.type_ == Type::Null) return "n";
+    throw std::runtime_error(
+        "Map keys must be numbers, characters, strings or null");
+}
+
+const Value* Value::find(const Value& key) const {
+    auto position = index_.find(get_key(key));
+    if (position == index_.end()) return nullptr;
+    return &values_[position->second];
+}
+
 Value::operator bool() const {
     return (is_value() && value_ != 0.0) || (is_iterable() && !list_.empty());
 }
@@ -44,6 +87,11 @@ Value& Value::operator=(const Value& other) {
         type_ = other.type_;
         value_ = other.value_;
         list_ = other.list_;
+        values_ = other.values_;
+        index_ = other.index_;
+        function_ = other.function_;
+        fields_ = other.fields_;
+        name_ = other.name_;
     }
     return *this;
 }
@@ -52,7 +100,8 @@ Value Value::operator+(const Value& other) const {
     if (is_value() && other.is_value())
         return type_ == Type::Float || other.type_ == Type::Float
                    ? Value(value_ + other.value_)
-                   : Value(static_cast<int>(value_ + other.value_));
+                   : Value(_iadd(static_cast<int32_t>(value_),
+                                 static_cast<int32_t>(other.value_)));
 
     if (type_ == Type::List && other.type_ == Type::List) {
         std::vector<Value> result = list_;
@@ -71,7 +120,8 @@ Value Value::operator-(const Value& other) const {
         throw std::runtime_error("Cannot subtract non-value types");
     if (type_ == Type::Float || other.type_ == Type::Float)
         return Value(value_ - other.value_);
-    return Value(static_cast<int>(value_ - other.value_));
+    return Value(_isub(static_cast<int32_t>(value_),
+                       static_cast<int32_t>(other.value_)));
 }
 
 Value Value::operator-() const { return Value(0) - *this; }
@@ -81,35 +131,27 @@ Value Value::operator*(const Value& other) const {
         throw std::runtime_error("Cannot multiply non-value types");
     if (type_ == Type::Float || other.type_ == Type::Float)
         return Value(value_ * other.value_);
-    return Value(static_cast<int>(value_ * other.value_));
+    return Value(_imul(static_cast<int32_t>(value_),
+                       static_cast<int32_t>(other.value_)));
 }
 
 Value Value::operator/(const Value& other) const {
     if (!is_value() || !other.is_value())
         throw std::runtime_error("Cannot divide non-value types");
+    if (type_ != Type::Float && other.type_ != Type::Float)
+        return Value(_idiv(static_cast<int32_t>(value_),
+                           static_cast<int32_t>(other.value_)));
     if (other.value_ == 0.0) throw std::runtime_error("Division by zero");
-    if (type_ == Type::Float || other.type_ == Type::Float)
-        return Value(value_ / other.value_);
-    return Value(static_cast<int>(value_ / other.value_));
+    return Value(value_ / other.value_);
 }
 
 Value Value::operator%(const Value& other) const {
     if (!is_value() || !other.is_value())
         throw std::runtime_error("Cannot use modulo with non-value types");
-    if (other.value_ == 0.0)
-        throw std::runtime_error("Division by zero in modulo operation");
-
-    double dividend = value_;
-    double divisor = other.value_;
-
-    double quotient = dividend / divisor;
-    long long int_quotient = static_cast<long long>(quotient);
-
-    double result = dividend - (divisor * int_quotient);
-
-    if (type_ == Type::Float || other.type_ == Type::Float)
-        return Value(result);
-    return Value(static_cast<int>(result));
+    if (type_ != Type::Float && other.type_ != Type::Float)
+        return Value(_imod(static_cast<int32_t>(value_),
+                           static_cast<int32_t>(other.value_)));
+    return Value(_fmod(value_, other.value_));
 }
 
 Value& Value::operator+=(const Value& other) {
@@ -149,7 +191,18 @@ Value& Value::operator|=(const Value& other) {
 
 bool Value::operator==(const Value& other) const {
     if (is_value() && other.is_value()) return value_ == other.value_;
+    if (type_ == Type::Map || other.type_ == Type::Map) {
+        if (type_ != other.type_ || list_.size() != other.list_.size())
+            return false;
+        for (size_t i = 0; i < list_.size(); ++i) {
+            const Value* value = other.find(list_[i]);
+            if (value == nullptr || !(*value == values_[i])) return false;
+        }
+        return true;
+    }
     if (is_iterable() && other.is_iterable()) return list_ == other.list_;
+    if (type_ == Type::Object && other.type_ == Type::Object)
+        return name_ == other.name_ && fields_ == other.fields_;
     return type_ == Type::Null && other.type_ == Type::Null;
 }
 
@@ -205,6 +258,13 @@ Value Value::operator--(int) {
 }
 
 Value& Value::operator[](Value index) {
+    if (type_ == Type::Map) {
+        const Value* value = find(index);
+        if (value == nullptr)
+            throw std::out_of_range("Key not found: " + index.to_string());
+        return values_[value - values_.data()];
+    }
+
     if (index.type_ == Type::String) {
         if (fields_.find(index.to_string()) != fields_.end())
             return fields_.at(index.to_string());
@@ -222,6 +282,13 @@ Value& Value::operator[](Value index) {
 }
 
 Value Value::operator[](Value index) const {
+    if (type_ == Type::Map) {
+        const Value* value = find(index);
+        if (value == nullptr)
+            throw std::out_of_range("Key not found: " + index.to_string());
+        return *value;
+    }
+
     if (index.type_ == Type::String && type_ == Type::Object) {
         if (fields_.find(index.to_string()) != fields_.end())
             return fields_.at(index.to_string());
@@ -238,58 +305,127 @@ Value Value::operator[](Value index) const {
 }
 
 Value Value::operator[](const char* method_name) {
-    if (global_methods_.find(method_name) == global_methods_.end()) {
-        throw std::runtime_error("Method '" + std::string(method_name) +
-                                 "' not found");
-    }
-
-    Value callable_method(
-        [this, method_name](const std::vector<Value>& args) -> Value {
-            std::vector<Value> method_args = {*this};
-            method_args.insert(method_args.end(), args.begin(), args.end());
-            return global_methods_[method_name](method_args);
-        });
-
-    return callable_method;
+    return std::as_const(*this)[method_name];
 }
 
 Value Value::operator[](const char* method_name) const {
-    if (global_methods_.find(method_name) == global_methods_.end()) {
+    // A field holding a function is called like a method.
+    if (type_ == Type::Object && fields_.count(method_name) != 0)
+        return fields_.at(method_name);
+
+    const auto* method = find_method(method_name);
+    if (method == nullptr) {
         throw std::runtime_error("Method '" + std::string(method_name) +
                                  "' not found");
     }
 
     Value callable_method(
-        [this, method_name](const std::vector<Value>& args) -> Value {
+        [this, method](const std::vector<Value>& args) -> Value {
             std::vector<Value> method_args = {*this};
             method_args.insert(method_args.end(), args.begin(), args.end());
-            return global_methods_[method_name](method_args);
+            return (*method)(method_args);
         });
 
     return callable_method;
 }
 
+// An object's struct's own method is found before a method of the same name
+// that doesn't name a struct.
+const std::function<Value(const std::vector<Value>&)>* Value::find_method(
+    const std::string& name) const {
+    if (const auto* method = struct_method(name)) return method;
+    auto method = global_methods().find(name);
+    return method == global_methods().end() ? nullptr : &method->second;
+}
+
+const std::function<Value(const std::vector<Value>&)>* Value::struct_method(
+    const std::string& name) const {
+    if (type_ != Type::Object) return nullptr;
+    auto method = global_methods().find(name_ + "." + name);
+    return method == global_methods().end() ? nullptr : &method->second;
+}
+
 Value Value::operator()() const {
     if (type_ != Type::Function)
         throw std::runtime_error("Value is not callable");
     return function_({});
 }
 
-std::ostream& operator<<(std::ostream& os, const Value& value) {
-    os << value.to_string();
-    return os;
+// The element an assignment writes to. Unlike indexing, assigning to a
+// missing key of a map adds it.
+Value& Value::entry(const Value& key) {
+    if (type_ != Type::Map) return (*this)[key];
+
+    auto [position, added] = index_.emplace(get_key(key), list_.size());
+    if (added) {
+        list_.push_back(key);
+        values_.push_back(Value(nullptr));
+    }
+    return values_[position->second];
 }
 
-std::vector<Value>::const_iterator Value::begin() const {
-    if (!is_iterable())
-        throw std::runtime_error("Cannot iterate non-iterable type");
-    return list_.begin();
+// The `in` operator: whether a map has a key, a list has an element, or a
+// string has a character or substring.
+bool Value::contains(const Value& item) const {
+    switch (type_) {
+        case Type::Map:
+            return find(item) != nullptr;
+        case Type::List:
+            for (const Value& element : list_)
+                if (element == item) return true;
+            return false;
+        case Type::String:
+            if (item.type_ != Type::String && item.type_ != Type::Char)
+                throw std::runtime_error(
+                    "Only characters and strings can be in a string");
+            return to_string().find(item.to_string()) != std::string::npos;
+        default:
+            throw std::runtime_error(
+                "Cannot check membership in non-iterable type");
+    }
 }
 
-std::vector<Value>::const_iterator Value::end() const {
-    if (!is_iterable())
-        throw std::runtime_error("Cannot iterate non-iterable type");
-    return list_.end();
+// A `match` literal: equal to the value and of the same type, counting ints
+// and floats as one.
+bool Value::matches_literal(const Value& literal) const {
+    bool is_number = type_ == Type::Int || type_ == Type::Float;
+    bool is_number_literal =
+        literal.type_ == Type::Int || literal.type_ == Type::Float;
+    if (is_number != is_number_literal ||
+        (!is_number && type_ != literal.type_))
+        return false;
+    return *this == literal;
+}
+
+// A `match` range: a number, or a character if the bounds are characters,
+// from `start` up to but not including `end`. A null bound is left open.
+bool Value::matches_range(const Value& start, const Value& end) const {
+    bool is_char = start.type_ == Type::Char || end.type_ == Type::Char;
+    if (is_char ? type_ != Type::Char
+                : type_ != Type::Int && type_ != Type::Float)
+        return false;
+    return (start.type_ == Type::Null || start <= *this) &&
+           (end.type_ == Type::Null || *this < end);
+}
+
+// A `match` list pattern: a list of exactly `count` items, or at least that
+// many when the pattern has a rest.
+bool Value::matches_list(size_t count, bool has_rest) const {
+    if (type_ != Type::List) return false;
+    return has_rest ? list_.size() >= count : list_.size() == count;
+}
+
+// The items of a list from `start` on, which a list pattern's rest binds.
+Value Value::slice(size_t start) const {
+    if (type_ != Type::List)
+        throw std::runtime_error("Cannot slice a non-list value");
+    if (start >= list_.size()) return Value(std::vector<Value>());
+    return Value(std::vector<Value>(list_.begin() + start, list_.end()));
+}
+
+std::ostream& operator<<(std::ostream& os, const Value& value) {
+    os << value.to_string();
+    return os;
 }
 
 std::string Value::to_string() const {
@@ -320,10 +456,20 @@ std::string Value::to_string() const {
             oss << "]";
             return oss.str();
         }
+        case Type::Map: {
+            std::ostringstream oss;
+            oss << "{";
+            for (size_t i = 0; i < list_.size(); ++i) {
+                if (i > 0) oss << ", ";
+                oss << list_[i].to_string() << ": " << values_[i].to_string();
+            }
+            oss << "}";
+            return oss.str();
+        }
         case Type::Function:
             return "function";
         case Type::Object:
-            return "object";
+            return name_;
     }
 
     return "";
@@ -335,16 +481,228 @@ double Value::get_value() const { return value_; }
 
 const std::vector<Value>& Value::get_list() const { return list_; }
 
+// Objects are made by the constructors generated for structs, which name
+// the struct and set each of its fields.
+Value Value::object(const std::string& name) {
+    Value value;
+    value.type_ = Type::Object;
+    value.name_ = name;
+    return value;
+}
+
 void Value::set_field(const std::string& name, const Value& value) {
     if (type_ != Type::Object)
         throw std::runtime_error("Value is not an object");
     fields_[name] = value;
 }
 
+Value& Value::field(const std::string& name) {
+    if (type_ != Type::Object)
+        throw std::runtime_error("Cannot access field '" + name +
+                                 "' of a non-object value");
+    auto field = fields_.find(name);
+    if (field == fields_.end())
+        throw std::runtime_error("'" + name_ + "' has no field '" + name + "'");
+    return field->second;
+}
+
+const Value& Value::field(const std::string& name) const {
+    if (type_ != Type::Object)
+        throw std::runtime_error("Cannot access field '" + name +
+                                 "' of a non-object value");
+    auto field = fields_.find(name);
+    if (field == fields_.end())
+        throw std::runtime_error("'" + name_ + "' has no field '" + name + "'");
+    return field->second;
+}
+
+const std::string& Value::get_name() const { return name_; }
+
 void Value::register_method(
     const std::string& name,
     std::function<Value(const std::vector<Value>&)> method) {
-    global_methods_[name] = method;
+    global_methods()[name] = method;
+}
+
+void Value::register_method(
+    const std::string& structure, const std::string& name,
+    std::function<Value(const std::vector<Value>&)> method) {
+    global_methods()[structure + "." + name] = method;
+}
+
+// Errors are `Error` objects, so a `catch` can read what went wrong and what
+// kind of error it was.
+static Value make_error(const std::string& message, const char* kind) {
+    Value error = Value::object("Error");
+    error.set_field("message", message.c_str());
+    error.set_field("kind", kind);
+    return error;
+}
+
+Iterator::Iterator(const Value& value) : count_(0), keyed_(false) {
+    Value source = value;
+    if (const auto* iter = source.struct_method("iter"))
+        source = (*iter)({source});
+    start(source);
+}
+
+bool Iterator::is_generator(const Value& value) {
+    return value.type_ == Type::Object && value.function_ != nullptr;
+}
+
+void Iterator::start(const Value& source) {
+    if (!source.is_iterable() && source.type_ != Type::Function &&
+        !is_generator(source))
+        throw std::runtime_error("Cannot iterate non-iterable type");
+    source_ = source;
+    position_ = 0;
+}
+
+bool Iterator::next() {
+    if (is_generator(source_)) {
+        Value step = source_.function_({});
+        if (step.type_ == Type::Null) return false;
+        key_ = Value(static_cast<int>(count_++));
+        value_ = step.list_[0];
+        keyed_ = false;
+        return true;
+    }
+
+    if (!source_.is_iterable()) {
+        Value step = source_();
+        if (step.type_ == Type::Null) return false;
+        if (step.type_ != Type::List || step.list_.size() != 2)
+            throw std::runtime_error(
+                "A step of a loop must give null or a list of the item and "
+                "the rest");
+        key_ = Value(static_cast<int>(count_++));
+        value_ = step.list_[0];
+        keyed_ = false;
+        start(step.list_[1]);
+        return true;
+    }
+
+    if (position_ >= source_.list_.size()) return false;
+    keyed_ = source_.type_ == Type::Map;
+    if (keyed_) {
+        key_ = source_.list_[position_];
+        value_ = source_.values_[position_];
+    } else {
+        key_ = Value(static_cast<int>(count_));
+        value_ = source_.list_[position_];
+    }
+    ++position_;
+    ++count_;
+    return true;
+}
+
+const Value& Iterator::item() const {
+    return keyed_ ? key_ : value_;
+}
+
+const Value& Iterator::key() const { return key_; }
+
+const Value& Iterator::value() const { return value_; }
+
+// What a generator's copies share. The body's closure is kept here, since
+// the coroutine it started reads its captures from it.
+struct GeneratorState {
+    std::function<GeneratorBody()> body;
+    GeneratorBody coroutine;
+    bool running = false;
+
+    Value step();
+
+    static Value make(const std::shared_ptr<GeneratorState>& state);
+};
+
+// The generator's steps, each a list of the item or null, are kept in its
+// hidden function, which a loop calls. Its `next` method gives the item, or
+// null once the body has returned.
+Value GeneratorState::make(const std::shared_ptr<GeneratorState>& state) {
+    static const bool registered = [] {
+        Value::register_method(
+            "Generator", "next", [](const std::vector<Value>& args) -> Value {
+                Value step = args[0].function_({});
+                if (step.type_ == Type::Null) return step;
+                return step.list_[0];
+            });
+        return true;
+    }();
+    (void)registered;
+
+    Value generator = Value::object("Generator");
+    generator.function_ = [state](const std::vector<Value>&) -> Value {
+        return state->step();
+    };
+    return generator;
+}
+
+Value GeneratorState::step() {
+    auto handle = coroutine.handle();
+    if (handle.done()) return Value(nullptr);
+    if (running)
+        throw std::runtime_error("A generator can't step itself");
+
+    running = true;
+    handle.resume();
+    running = false;
+
+    auto& promise = handle.promise();
+    if (promise.error) {
+        std::exception_ptr thrown = promise.error;
+        promise.error = nullptr;
+        std::rethrow_exception(thrown);
+    }
+    if (handle.done()) return Value(nullptr);
+    return Value(std::vector<Value>{promise.item});
+}
+
+GeneratorBody::GeneratorBody(GeneratorBody&& other) noexcept
+    : handle_(std::exchange(other.handle_, nullptr)) {}
+
+GeneratorBody& GeneratorBody::operator=(GeneratorBody&& other) noexcept {
+    if (this != &other) {
+        if (handle_) handle_.destroy();
+        handle_ = std::exchange(other.handle_, nullptr);
+    }
+    return *this;
+}
+
+GeneratorBody::~GeneratorBody() {
+    if (handle_) handle_.destroy();
+}
+
+Value _generator(std::function<GeneratorBody()> body) {
+    auto state = std::make_shared<GeneratorState>();
+    state->body = std::move(body);
+    state->coroutine = state->body();
+    return GeneratorState::make(state);
+}
+
+Thrown::Thrown(const Value& value)
+    : value_(value.get_type() == Type::Object
+                 ? value
+                 : make_error(value.to_string(), "error")) {
+    // An object without a `message` field is described by its struct's name.
+    const Value& value_ref = value_;
+    const Value message = value_ref[Value("message")];
+    message_ = message.get_type() == Type::Null ? value_.to_string()
+                                                : message.to_string();
+}
+
+const char* Thrown::what() const noexcept { return message_.c_str(); }
+
+const Value& Thrown::get_value() const { return value_; }
+
+// Runtime errors are caught as errors of kind "index" for missing indices
+// and keys, and "runtime" for everything else.
+Value _caught(const std::exception& error) {
+    if (auto thrown = dynamic_cast<const Thrown*>(&error))
+        return thrown->get_value();
+    if (dynamic_cast<const std::out_of_range*>(&error))
+        return make_error(error.what(), "index");
+    return make_error(error.what(), "runtime");
 }
 
 Value _type(const Value& value) {
@@ -363,16 +721,19 @@ Value _type(const Value& value) {
             return "string";
         case Type::List:
             return "list";
+        case Type::Map:
+            return "map";
         case Type::Function:
             return "function";
         case Type::Object:
-            return "object";
+            return value.get_name().c_str();
     }
     return "";
 }
 
 Value _len(const Value& value) {
-    if (value.get_type() == Type::List || value.get_type() == Type::String)
+    if (value.get_type() == Type::List || value.get_type() == Type::String ||
+        value.get_type() == Type::Map)
         return Value(static_cast<int>(value.get_list().size()));
     throw std::runtime_error("Cannot get length of non-list type");
 }
@@ -380,13 +741,15 @@ Value _len(const Value& value) {
 Value _null(const Value&) { return Value(nullptr); }
 
 Value _int(const Value& value) {
-    if (value.get_type() == Type::List || value.get_type() == Type::String)
+    if (value.get_type() == Type::List || value.get_type() == Type::String ||
+        value.get_type() == Type::Map)
         throw std::runtime_error("Cannot convert non-value type to int");
-    return Value(static_cast<int>(value.get_value()));
+    return Value(_ftoi(value.get_value()));
 }
 
 Value _float(const Value& value) {
-    if (value.get_type() == Type::List || value.get_type() == Type::String)
+    if (value.get_type() == Type::List || value.get_type() == Type::String ||
+        value.get_type() == Type::Map)
         throw std::runtime_error("Cannot convert non-value type to float");
     return Value(value.get_value());
 }
@@ -394,7 +757,8 @@ Value _float(const Value& value) {
 Value _bool(const Value& value) { return (bool)value; }
 
 Value _char(const Value& value) {
-    if (value.get_type() == Type::List || value.get_type() == Type::String)
+    if (value.get_type() == Type::List || value.get_type() == Type::String ||
+        value.get_type() == Type::Map)
         throw std::runtime_error("Cannot convert non-value type to char");
     return Value(static_cast<char>(value.get_value()));
 }
@@ -403,6 +767,8 @@ Value _string(const Value& value) { return Value(value.to_string().c_str()); }
 
 Value _list(const Value& value) {
     if (value.get_type() == Type::List) return value;
-    if (value.get_type() == Type::String) return Value(value.get_list());
+    // A map becomes the list of its keys.
+    if (value.get_type() == Type::String || value.get_type() == Type::Map)
+        return Value(value.get_list());
     return Value(std::vector<Value>{value});
 }
\ No newline at end of file
@@ -1,8 +1,13 @@
 #ifndef STD_HPP
 #define STD_HPP
 
+#include <cmath>
+#include <coroutine>
+#include <cstdint>
+#include <exception>
 #include <functional>
 #include <iostream>
+#include <optional>
 #include <sstream>
 #include <stdexcept>
 #include <string>
@@ -17,6 +22,7 @@ enum class Type {
     Float,
     String,
     List,
+    Map,
     Function,
     Object
 };
@@ -25,17 +31,29 @@ class Value {
    private:
     Type type_;
     double value_;
+    // A map keeps its keys in `list_` in insertion order, with each key's
+    // value at the same position in `values_`.
     std::vector<Value> list_;
+    std::vector<Value> values_;
+    std::unordered_map<std::string, size_t> index_;
     std::function<Value(const std::vector<Value>&)> function_;
     std::unordered_map<std::string, Value> fields_;
+    std::string name_;
 
     static std::unordered_map<std::string,
-                              std::function<Value(const std::vector<Value>&)>>
-        global_methods_;
+                              std::function<Value(const std::vector<Value>&)>>&
+    global_methods();
 
     bool is_iterable() const {
-        return type_ == Type::List || type_ == Type::String;
+        return type_ == Type::List || type_ == Type::String ||
+               type_ == Type::Map;
     }
+    static std::string get_key(const Value& key);
+    const Value* find(const Value& key) const;
+    const std::function<Value(const std::vector<Value>&)>* find_method(
+        const std::string& name) const;
+    const std::function<Value(const std::vector<Value>&)>* struct_method(
+        const std::string& name) const;
     bool is_value() const {
         return type_ == Type::Int || type_ == Type::Float ||
                type_ == Type::Bool || type_ == Type::Char;
@@ -52,6 +70,7 @@ class Value {
     Value(const char* val);
     Value(const std::vector<Value>& val);
     Value(std::function<Value(const std::vector<Value>&)> func);
+    Value(const std::vector<std::pair<Value, Value>>& entries);
 
     explicit operator bool() const;
 
@@ -95,10 +114,16 @@ class Value {
 
     Value operator()() const;
 
-    friend std::ostream& operator<<(std::ostream& os, const Value& var);
+    Value& entry(const Value& key);
+    bool contains(const Value& item) const;
+    bool matches_literal(const Value& literal) const;
+    bool matches_range(const Value& start, const Value& end) const;
+    bool matches_list(size_t count, bool has_rest) const;
+    Value slice(size_t start) const;
 
-    std::vector<Value>::const_iterator begin() const;
-    std::vector<Value>::const_iterator end() const;
+    friend std::ostream& operator<<(std::ostream& os, const Value& var);
+    friend class Iterator;
+    friend struct GeneratorState;
 
     std::string to_string() const;
 
@@ -106,11 +131,20 @@ class Value {
     double get_value() const;
     const std::vector<Value>& get_list() const;
 
+    static Value object(const std::string& name);
     void set_field(const std::string& name, const Value& value);
+    Value& field(const std::string& name);
+    const Value& field(const std::string& name) const;
+    const std::string& get_name() const;
 
     static void register_method(
         const std::string& name,
         std::function<Value(const std::vector<Value>&)> method);
+    // Registers a method of one struct, which its objects find before a
+    // method of the same name that doesn't name a struct.
+    static void register_method(
+        const std::string& structure, const std::string& name,
+        std::function<Value(const std::vector<Value>&)> method);
 
     template <typename... Args>
     Value operator()(const Args&... args) const {
@@ -122,6 +156,68 @@ class Value {
     }
 };
 
+// Walks what `loop ... in` goes over. Lists, strings and maps are walked in
+// place. A generator is stepped by its `next`, which gives the next item or
+// null once there are none, and is the only `next` a loop calls. A function
+// is a cursor called with no arguments, giving null when nothing is left or
+// a list of the item and what walks the rest, so it can be lazy and endless.
+// An object whose struct has an `iter` method is walked as what that gives;
+// fields and methods that don't name a struct are never used.
+class Iterator {
+   private:
+    Value source_;
+    size_t position_;
+    size_t count_;
+    Value key_;
+    Value value_;
+    bool keyed_;
+
+    static bool is_generator(const Value& value);
+    void start(const Value& source);
+
+   public:
+    explicit Iterator(const Value& value);
+    Iterator(const Iterator&) = delete;
+    Iterator& operator=(const Iterator&) = delete;
+
+    // Moves to the next item, giving false once there are none left.
+    bool next();
+    // What a single loop variable holds: a map's key, or the item itself.
+    const Value& item() const;
+    // What a pair of loop variables hold: a map's key and value, or the
+    // item's position and the item.
+    const Value& key() const;
+    const Value& value() const;
+};
+
+// What a Lyra `throw` raises. A value that isn't an object is wrapped in an
+// `Error`, with the value as its message.
+class Thrown : public std::exception {
+   private:
+    Value value_;
+    std::string message_;
+
+   public:
+    explicit Thrown(const Value& value);
+    const char* what() const noexcept override;
+    const Value& get_value() const;
+};
+
+// Runs a `finally` block when the scope holding it is left, however that
+// happens. An error the block throws while another is already on its way
+// out ends the program, since C++ can't carry both.
+template <typename F>
+class Finally {
+   private:
+    F block_;
+
+   public:
+    explicit Finally(F block) : block_(block) {}
+    Finally(const Finally&) = delete;
+    Finally& operator=(const Finally&) = delete;
+    ~Finally() noexcept(false) { block_(); }
+};
+
 class Range {
    private:
     int start_;
@@ -151,7 +247,7 @@ class Range {
         }
 
         bool operator!=(const iterator& other) const {
-            return current_ != other.current_;
+            return current_ < other.current_;
         }
     };
 
@@ -159,6 +255,100 @@ class Range {
     iterator end() const { return iterator(end_, end_); }
 };
 
+// Int arithmetic, shared by Values and the native ints of release builds so
+// both give the same results. Ints are 32 bits and wrap around when they
+// overflow, and dividing by zero throws.
+inline int32_t _wrap(int64_t value) {
+    return static_cast<int32_t>(static_cast<uint32_t>(value));
+}
+
+inline int32_t _iadd(int32_t left, int32_t right) {
+    return _wrap(int64_t{left} + right);
+}
+
+inline int32_t _isub(int32_t left, int32_t right) {
+    return _wrap(int64_t{left} - right);
+}
+
+inline int32_t _imul(int32_t left, int32_t right) {
+    return _wrap(int64_t{left} * right);
+}
+
+inline int32_t _idiv(int32_t dividend, int32_t divisor) {
+    if (divisor == 0) throw std::runtime_error("Division by zero");
+    return _wrap(int64_t{dividend} / divisor);
+}
+
+inline int32_t _imod(int32_t dividend, int32_t divisor) {
+    if (divisor == 0)
+        throw std::runtime_error("Division by zero in modulo operation");
+    return _wrap(int64_t{dividend} % divisor);
+}
+
+// Truncates a float to an int, wrapping the way int arithmetic does. A
+// float that isn't finite becomes 0.
+inline int32_t _ftoi(double value) {
+    double truncated = std::fmod(std::trunc(value), 4294967296.0);
+    return std::isfinite(truncated) ? _wrap(static_cast<int64_t>(truncated)) : 0;
+}
+
+inline double _fdiv(double dividend, double divisor) {
+    if (divisor == 0.0) throw std::runtime_error("Division by zero");
+    return dividend / divisor;
+}
+
+inline double _fmod(double dividend, double divisor) {
+    if (divisor == 0.0)
+        throw std::runtime_error("Division by zero in modulo operation");
+    return dividend - divisor * static_cast<long long>(dividend / divisor);
+}
+
+Value _caught(const std::exception& error);
+
+// The coroutine a generator's body is compiled to. It starts suspended and
+// stops at each `co_yield`, keeping the item, or any error it ends with, for
+// whoever resumed it. Destroying a suspended one destroys its locals as if
+// its body were left there, so its `finally` blocks run.
+class GeneratorBody {
+   public:
+    struct promise_type {
+        Value item;
+        std::exception_ptr error;
+
+        GeneratorBody get_return_object() {
+            return GeneratorBody(
+                std::coroutine_handle<promise_type>::from_promise(*this));
+        }
+        std::suspend_always initial_suspend() noexcept { return {}; }
+        std::suspend_always final_suspend() noexcept { return {}; }
+        std::suspend_always yield_value(const Value& value) {
+            item = value;
+            return {};
+        }
+        void return_void() noexcept {}
+        void unhandled_exception() { error = std::current_exception(); }
+    };
+
+    GeneratorBody() = default;
+    explicit GeneratorBody(std::coroutine_handle<promise_type> handle)
+        : handle_(handle) {}
+    GeneratorBody(GeneratorBody&& other) noexcept;
+    GeneratorBody& operator=(GeneratorBody&& other) noexcept;
+    ~GeneratorBody();
+
+    std::coroutine_handle<promise_type> handle() const { return handle_; }
+
+   private:
+    std::coroutine_handle<promise_type> handle_;
+};
+
+// Starts a generator, whose body runs a step at a time from the first `next`
+// on, up to each `yield`, as `Iterator` describes. A generator is only a
+// coroutine, so any number of them can be live at once. Once its last copy
+// is dropped, a body still waiting is destroyed there, running its `finally`
+// blocks, which is how a `break` out of a loop over it stops it.
+Value _generator(std::function<GeneratorBody()> body);
+
 Value _type(const Value& value);
 
 Value _len(const Value& value);
@@ -0,0 +1,229 @@
+use crate::ast::*;
+use crate::types::Span;
+use std::collections::HashMap;
+
+/// Every module's top-level names, and the functions and methods calls
+/// are checked against. A struct's constructor is checked as a function
+/// taking its fields.
+pub struct Symbols<'a> {
+    pub exports: HashMap<&'a str, HashMap<&'a str, Binding>>,
+    pub functions: HashMap<(&'a str, &'a str), Signature<'a>>,
+    pub methods: HashMap<&'a str, Vec<Signature<'a>>>,
+}
+
+/// The parameters and return type of a function or method, and where it
+/// is defined.
+pub struct Signature<'a> {
+    pub params: &'a [Parameter],
+    pub return_type: Option<Type>,
+    pub span: &'a Span,
+}
+
+impl<'a> Signature<'a> {
+    /// The parameters after the first `skip`, which leaves out a method's
+    /// receiver.
+    pub fn params_from(&self, skip: usize) -> &'a [Parameter] {
+        &self.params[skip.min(self.params.len())..]
+    }
+}
+
+pub fn get_symbols(modules: &[Module]) -> Symbols<'_> {
+    let mut symbols = Symbols {
+        exports: HashMap::new(),
+        functions: HashMap::new(),
+        methods: HashMap::new(),
+    };
+
+    for module in modules {
+        // Nothing can be imported from `main.ly`, but its structs are
+        // still constructed.
+        if module.name != "main" {
+            symbols
+                .exports
+                .insert(module.name.as_str(), get_exports(module));
+        }
+
+        for statement in &module.statements {
+            match &statement.kind {
+                StatementKind::Function {
+                    name,
+                    params,
+                    return_type,
+                    ..
+                } => {
+                    symbols.functions.insert(
+                        (module.name.as_str(), name.as_str()),
+                        Signature {
+                            params,
+                            return_type: *return_type,
+                            span: &statement.span,
+                        },
+                    );
+                }
+                StatementKind::Method {
+                    name,
+                    params,
+                    return_type,
+                    ..
+                } => {
+                    symbols
+                        .methods
+                        .entry(name.as_str())
+                        .or_default()
+                        .push(Signature {
+                            params,
+                            return_type: *return_type,
+                            span: &statement.span,
+                        });
+                }
+                StatementKind::Struct { name, fields } => {
+                    symbols.functions.insert(
+                        (module.name.as_str(), name.as_str()),
+                        Signature {
+                            params: fields,
+                            return_type: None,
+                            span: &statement.span,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    symbols
+}
+
+/// The names other modules can reach through `module::name`.
+fn get_exports(module: &Module) -> HashMap<&str, Binding> {
+    module
+        .statements
+        .iter()
+        .filter_map(|statement| match &statement.kind {
+            StatementKind::Let { name, .. } => Some((name.as_str(), Binding::Variable)),
+            StatementKind::Const { name, .. } => Some((name.as_str(), Binding::Constant)),
+            StatementKind::Function { name, .. } => Some((name.as_str(), Binding::Function)),
+            StatementKind::Struct { name, .. } => Some((name.as_str(), Binding::Struct)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// What fills one parameter of a call to a known function, method or
+/// struct.
+#[derive(Debug, Clone)]
+pub enum Slot {
+    /// The argument at this index.
+    Argument(usize),
+    /// Nothing, so the parameter gets its default, or `null` without one.
+    Default,
+    /// The arguments at these indices, collected by a rest parameter.
+    Rest(Vec<usize>),
+}
+
+/// Matches a call's arguments to `params`: positional arguments fill the
+/// parameters in order, with extra ones going to a rest parameter, and
+/// named arguments fill the parameters with their names. `name` describes
+/// what is called for the error reported when they don't fit.
+pub fn bind_arguments(
+    name: &str,
+    params: &[Parameter],
+    arguments: usize,
+    names: &[String],
+) -> Result<Vec<Slot>, String> {
+    let positional = arguments - names.len();
+    let fixed = params.iter().filter(|param| !param.rest).count();
+    let required = params
+        .iter()
+        .filter(|param| !param.rest && param.default.is_none())
+        .count();
+    let has_rest = fixed < params.len();
+
+    let count_error = || {
+        let expected = if has_rest {
+            format!("at least {}", count_arguments(required))
+        } else if required < fixed {
+            format!("{required} to {}", count_arguments(fixed))
+        } else {
+            count_arguments(fixed)
+        };
+        format!(
+            "{name} takes {expected} but {arguments} {} given",
+            if arguments == 1 { "was" } else { "were" }
+        )
+    };
+
+    if positional > fixed && !has_rest {
+        return Err(count_error());
+    }
+
+    let mut slots = (0..fixed)
+        .map(|i| {
+            if i < positional {
+                Slot::Argument(i)
+            } else {
+                Slot::Default
+            }
+        })
+        .collect::<Vec<_>>();
+    if has_rest {
+        slots.push(Slot::Rest((fixed..positional).collect()));
+    }
+
+    for (i, argument) in names.iter().enumerate() {
+        let Some(index) = params
+            .iter()
+            .position(|param| !param.rest && param.name == *argument)
+        else {
+            return Err(format!("{name} has no parameter named `{argument}`"));
+        };
+        if !matches!(slots[index], Slot::Default) {
+            return Err(format!(
+                "`{argument}` is passed more than once in this call to {name}"
+            ));
+        }
+        slots[index] = Slot::Argument(positional + i);
+    }
+
+    let missing = params.iter().zip(&slots).find(|(param, slot)| {
+        !param.rest && param.default.is_none() && matches!(slot, Slot::Default)
+    });
+    match missing {
+        Some(_) if names.is_empty() => Err(count_error()),
+        Some((param, _)) => Err(format!(
+            "{name} is missing an argument for `{}`",
+            param.name
+        )),
+        None => Ok(slots),
+    }
+}
+
+fn count_arguments(count: usize) -> String {
+    format!("{count} argument{}", if count == 1 { "" } else { "s" })
+}
+
+/// What a name refers to.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Binding {
+    Variable,
+    Constant,
+    LoopVariable,
+    Function,
+    Struct,
+    Builtin,
+}
+
+impl Binding {
+    /// How the binding is described when it can't be assigned to, or `None`
+    /// if it can.
+    pub fn describe_immutable(&self) -> Option<&'static str> {
+        match self {
+            Binding::Variable => None,
+            Binding::Constant => Some("constant"),
+            Binding::LoopVariable => Some("loop variable"),
+            Binding::Function => Some("function"),
+            Binding::Struct => Some("struct"),
+            Binding::Builtin => Some("built-in function"),
+        }
+    }
+}
@@ -3,8 +3,12 @@ pub type Writer = std::io::BufWriter<std::fs::File>;
 
 pub struct Method {
     pub method: String,
+    /// The struct the method is only called on, if it names one.
+    pub receiver: Option<String>,
     pub args_str: String,
-    pub num_params: usize,
+    pub min_params: usize,
+    /// `None` when a rest parameter takes any number of extra arguments.
+    pub max_params: Option<usize>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -27,6 +31,23 @@ pub enum SpecialCharacter {
     CloseBracket,
     OpenBrace,
     CloseBrace,
+    DoubleEquals,
+    NotEquals,
+    SmallerThanOrEquals,
+    LargerThanOrEquals,
+    PlusEquals,
+    MinusEquals,
+    AsteriskEquals,
+    SlashEquals,
+    PercentEquals,
+    DoubleColon,
+    DoubleDot,
+    Ellipsis,
+    DoublePlus,
+    DoubleMinus,
+    Arrow,
+    FatArrow,
+    Backslash,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -34,12 +55,19 @@ pub enum Keyword {
     Let,
     Const,
     Method,
+    Struct,
     Return,
     Import,
     If,
     Else,
     Loop,
     In,
+    Match,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Yield,
     True,
     False,
     Null,
@@ -58,9 +86,182 @@ pub enum Literal {
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum Token {
+pub enum TokenKind {
     Identifier(String),
     Keyword(Keyword),
     SpecialCharacter(SpecialCharacter),
     Literal(Literal),
 }
+
+/// Where a token came from: the source file, the 1-based line and column of
+/// its first character, and its byte range within that line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns a span running from the start of `self` to the end of `other`.
+    /// Spans that end on a later line are cut at the end of `self`.
+    pub fn to(&self, other: &Span) -> Span {
+        let end = if other.line == self.line && other.end > self.start {
+            other.end
+        } else {
+            self.end
+        };
+
+        Span {
+            end,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    UnterminatedCharacter,
+    EmptyCharacter,
+    InvalidEscapeSequence(char),
+    UnexpectedToken(String),
+    UnclosedBracket(char),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl std::fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::UnterminatedCharacter => write!(f, "unterminated character literal"),
+            LexErrorKind::EmptyCharacter => write!(f, "empty character literal"),
+            LexErrorKind::InvalidEscapeSequence(c) => write!(f, "invalid escape sequence: \\{c}"),
+            LexErrorKind::UnexpectedToken(token) => write!(f, "unexpected token: {token}"),
+            LexErrorKind::UnclosedBracket(bracket) => write!(f, "unclosed `{bracket}`"),
+        }
+    }
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: error: {}",
+            self.span.file, self.span.line, self.span.column, self.kind
+        )
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// One logical line: the tokens of a physical line plus any continuation
+/// lines, together with the indentation of the first one.
+pub struct Line {
+    pub tokens: Vec<Token>,
+    pub indent: u8,
+    pub has_errors: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: error: {}",
+            self.span.file, self.span.line, self.span.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem in the source, collected from any compiler pass and reported
+/// together once the front end has run over every file. Only errors stop
+/// the build. Notes point at related code, such as the definition a call
+/// doesn't match.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<(String, Span)>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: &Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: span.clone(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: &Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message, span)
+        }
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, span: &Span) -> Self {
+        self.notes.push((message.into(), span.clone()));
+        self
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        Diagnostic::error(error.kind.to_string(), &error.span)
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic::error(error.message, &error.span)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.span.file, self.span.line, self.span.column, self.severity, self.message
+        )
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}